@@ -0,0 +1,82 @@
+#![no_main]
+#![no_std]
+
+//! Example of sharing the I2C bus between the MMC5983 driver and another
+//! device with the async feature.
+//! Uses [embassy](https://embassy.dev) for the HAL, the executor and the
+//! shared bus. The micro:bit v2 LSM303AGR accelerometer sits on the same bus.
+//!
+//! Make sure [probe-rs](https://probe.rs) is installed.
+//!
+//! Run it using
+//!
+//! ```sh
+//! cargo run --example microbit-v2-shared-bus --target thumbv7em-none-eabihf --features async
+//! ```
+
+use embassy_embedded_hal::shared_bus::asynch::i2c::I2cDevice;
+use embassy_nrf::{self as hal, twim::Twim};
+use embassy_sync::{blocking_mutex::raw::NoopRawMutex, mutex::Mutex};
+use embassy_time::Delay;
+use embedded_hal_async::{delay::DelayNs, i2c::I2c};
+use hal::twim;
+use mmc5983_rs::Mmc5983Async;
+use rtt_target::{rprintln, rtt_init_print};
+
+use panic_rtt_target as _; // Panic handler
+
+/// LSM303AGR accelerometer address and WHO_AM_I register
+const ACCEL_ADDR: u8 = 0x19;
+const WHO_AM_I_A: u8 = 0x0F;
+
+hal::bind_interrupts!(struct Irqs {
+    SPIM0_SPIS0_TWIM0_TWIS0_SPI0_TWI0 => twim::InterruptHandler<hal::peripherals::TWISPI0>;
+});
+
+#[embassy_executor::main]
+async fn main(_s: embassy_executor::Spawner) -> ! {
+    // Init RTT control block
+    rtt_init_print!();
+
+    let dp = hal::init(Default::default());
+
+    rprintln!("Starting");
+
+    let config = twim::Config::default();
+    let twim0 = Twim::new(dp.TWISPI0, Irqs, dp.P0_16, dp.P0_08, config);
+    // Every device gets its own handle to the bus, which is locked for the
+    // duration of each transaction
+    let bus = Mutex::<NoopRawMutex, _>::new(twim0);
+
+    let mut sensor = Mmc5983Async::new_with_i2c(I2cDevice::new(&bus));
+    let mut accel = I2cDevice::new(&bus);
+
+    sensor.init().await.unwrap();
+    let mut delay = Delay;
+    let offset = sensor.calibrate_offset(&mut delay).await.unwrap();
+    rprintln!("Calibrated with offset: {:?}", offset);
+
+    let mut who_am_i = [0];
+    accel
+        .write_read(ACCEL_ADDR, &[WHO_AM_I_A], &mut who_am_i)
+        .await
+        .unwrap();
+    rprintln!("Accelerometer WHO_AM_I: {:#04x}", who_am_i[0]);
+
+    loop {
+        match sensor.magnetic_field().await {
+            Ok(data) => {
+                rprintln!(
+                    "Magnetic field: x {} y {} z {}",
+                    data.x_gauss(),
+                    data.y_gauss(),
+                    data.z_gauss()
+                );
+            }
+            Err(err) => {
+                rprintln!("Some error occurred {:?}", err)
+            }
+        }
+        Delay.delay_ms(200).await;
+    }
+}
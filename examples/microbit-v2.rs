@@ -16,7 +16,7 @@ use embassy_nrf::{self as hal, twim::Twim};
 use embassy_time::Delay;
 use embedded_hal_async::delay::DelayNs;
 use hal::twim;
-use mmc5983_rs::Mmc5983;
+use mmc5983_rs::Mmc5983Async;
 use rtt_target::{rprintln, rtt_init_print};
 
 use panic_rtt_target as _; // Panic handler
@@ -39,7 +39,7 @@ async fn main(_s: embassy_executor::Spawner) -> ! {
     let config = twim::Config::default();
     let twim0 = Twim::new(dp.TWISPI0, Irqs, dp.P0_16, dp.P0_08, config);
 
-    let mut sensor = Mmc5983::new_with_i2c(twim0);
+    let mut sensor = Mmc5983Async::new_with_i2c(twim0);
     let id = sensor.product_id().await.unwrap();
     rprintln!("{:#02x?}", id);
 
@@ -0,0 +1,355 @@
+//! Command-line tool for the MMC5983 on Linux
+//!
+//! Build it with the `cli` feature:
+//!
+//! ```sh
+//! cargo run --features cli --bin mmc5983 -- --i2c /dev/i2c-1 read --count 10
+//! ```
+
+use std::{
+    env,
+    fmt::Debug,
+    process::ExitCode,
+    thread,
+    time::{Duration, Instant},
+};
+
+use linux_embedded_hal::{
+    spidev::{SpiModeFlags, Spidev, SpidevOptions},
+    Delay, I2cdev, SpidevDevice,
+};
+use mmc5983_rs::{
+    interface::{ReadData, WriteData},
+    mode, BandwidthMode, Config, Error, MagOutputDataRate, Mmc5983, Resolution, SelfTestLimits,
+    DEFAULT_MAX_POLLS,
+};
+
+const USAGE: &str = "\
+Usage: mmc5983 (--i2c <DEVICE> | --spi <DEVICE>) [OPTIONS] <COMMAND>
+
+Commands:
+  id          Read and check the product ID
+  dump-regs   Print all readable registers
+  read        Take calibrated one-shot measurements
+  stream      Print continuous mode measurements
+  temp        Read the temperature
+  calibrate   Measure the bridge offset
+  self-test   Run the built-in self-test
+
+Options:
+  --i2c <DEVICE>        I2C bus, e.g. /dev/i2c-1
+  --spi <DEVICE>        SPI device, e.g. /dev/spidev1.1
+  --rate <HZ>           Continuous output data rate: 1, 10, 20, 50, 100, 200 or 1000 [default: 100]
+  --bandwidth <HZ>      Measurement bandwidth: 100, 200, 400 or 800 [default: lowest supporting the rate]
+  --resolution <BITS>   Output resolution: 16 or 18 [default: 18]
+  --count <N>           Number of measurements for read and stream [default: 1 for read, unlimited for stream]
+  --min-delta <GAUSS>   Smallest accepted self-test delta [default: 0.1]
+  --max-delta <GAUSS>   Largest accepted self-test delta [default: 8]
+  -h, --help            Print this help";
+
+/// SPI clock used for the SPI interface
+const SPI_SPEED_HZ: u32 = 1_000_000;
+
+#[derive(Debug)]
+enum Bus {
+    I2c(String),
+    Spi(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Command {
+    Id,
+    DumpRegs,
+    Read,
+    Stream,
+    Temp,
+    Calibrate,
+    SelfTest,
+}
+
+#[derive(Debug)]
+struct Options {
+    bus: Bus,
+    command: Command,
+    config: Config,
+    count: Option<u32>,
+    limits: SelfTestLimits,
+}
+
+fn parse_value<T: std::str::FromStr>(option: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{option} needs a value"))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value for {option}: {value}"))
+}
+
+fn parse_rate(hz: u32) -> Result<MagOutputDataRate, String> {
+    Ok(match hz {
+        1 => MagOutputDataRate::Hz1,
+        10 => MagOutputDataRate::Hz10,
+        20 => MagOutputDataRate::Hz20,
+        50 => MagOutputDataRate::Hz50,
+        100 => MagOutputDataRate::Hz100,
+        200 => MagOutputDataRate::Hz200,
+        1000 => MagOutputDataRate::Hz1000,
+        _ => return Err(format!("unsupported output data rate: {hz} Hz")),
+    })
+}
+
+fn parse_bandwidth(hz: u32) -> Result<BandwidthMode, String> {
+    Ok(match hz {
+        100 => BandwidthMode::Hz100,
+        200 => BandwidthMode::Hz200,
+        400 => BandwidthMode::Hz400,
+        800 => BandwidthMode::Hz800,
+        _ => return Err(format!("unsupported bandwidth: {hz} Hz")),
+    })
+}
+
+fn parse_command(name: &str) -> Result<Command, String> {
+    Ok(match name {
+        "id" => Command::Id,
+        "dump-regs" => Command::DumpRegs,
+        "read" => Command::Read,
+        "stream" => Command::Stream,
+        "temp" => Command::Temp,
+        "calibrate" => Command::Calibrate,
+        "self-test" => Command::SelfTest,
+        _ => return Err(format!("unknown command: {name}")),
+    })
+}
+
+/// Parse the command line, `Ok(None)` when help was requested
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut bus = None;
+    let mut command = None;
+    let mut rate = MagOutputDataRate::Hz100;
+    let mut bandwidth = None;
+    let mut resolution = Resolution::Bits18;
+    let mut count = None;
+    let (mut min_delta, mut max_delta) = (0.1, 8.0);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--i2c" => bus = Some(Bus::I2c(parse_value(&arg, args.next())?)),
+            "--spi" => bus = Some(Bus::Spi(parse_value(&arg, args.next())?)),
+            "--rate" => rate = parse_rate(parse_value(&arg, args.next())?)?,
+            "--bandwidth" => bandwidth = Some(parse_bandwidth(parse_value(&arg, args.next())?)?),
+            "--resolution" => {
+                resolution = match parse_value(&arg, args.next())? {
+                    16 => Resolution::Bits16,
+                    18 => Resolution::Bits18,
+                    bits => return Err(format!("unsupported resolution: {bits} bits")),
+                }
+            }
+            "--count" => count = Some(parse_value(&arg, args.next())?),
+            "--min-delta" => min_delta = parse_value(&arg, args.next())?,
+            "--max-delta" => max_delta = parse_value(&arg, args.next())?,
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {arg}")),
+            _ if command.is_some() => return Err(format!("unexpected argument: {arg}")),
+            _ => command = Some(parse_command(&arg)?),
+        }
+    }
+
+    let config = Config::new()
+        .with_output_rate(rate)
+        .with_bandwidth(bandwidth.unwrap_or(BandwidthMode::for_output_rate(rate)))
+        .with_resolution(resolution);
+    config.validate().map_err(|e| format!("{e:?}"))?;
+
+    Ok(Some(Options {
+        bus: bus.ok_or("select a bus with --i2c or --spi")?,
+        command: command.ok_or("missing command")?,
+        config,
+        count,
+        limits: SelfTestLimits::new(min_delta, max_delta),
+    }))
+}
+
+/// Status reads allowed while waiting for the first continuous measurement,
+/// which takes up to one output period. A read takes at least 10 µs even on a
+/// fast bus.
+fn stream_max_polls(rate: MagOutputDataRate) -> u32 {
+    (rate.period_us() / 10).max(DEFAULT_MAX_POLLS)
+}
+
+/// Run the command, returning whether it succeeded
+fn run<DI, CommE>(
+    mut mag: Mmc5983<DI, mode::OneShot>,
+    options: &Options,
+) -> Result<bool, Error<CommE>>
+where
+    DI: ReadData<Error = Error<CommE>> + WriteData<Error = Error<CommE>>,
+{
+    let mut delay = Delay;
+
+    match options.command {
+        Command::Id => {
+            let id = mag.product_id()?;
+            let verdict = if id.is_correct() { "ok" } else { "unexpected" };
+            println!("product id: {:#04x} ({verdict})", id.raw());
+            return Ok(id.is_correct());
+        }
+        Command::DumpRegs => {
+            let dump = mag.dump_registers()?;
+            for (addr, value) in dump.output().iter().enumerate() {
+                println!("{addr:#04x}: {value:#04x}");
+            }
+            println!("0x07: {:#04x}", dump.temperature());
+            println!("0x08: {:#04x}", dump.status());
+            println!("0x2f: {:#04x}", dump.product_id());
+            return Ok(true);
+        }
+        _ => {}
+    }
+
+    mag.init_with_delay(options.config, &mut delay)?;
+
+    match options.command {
+        Command::Read => {
+            mag.calibrate_offset(&mut delay)?;
+            for _ in 0..options.count.unwrap_or(1) {
+                let (x, y, z) = mag.get_calibrated_field()?.gauss();
+                println!("{x:.6} {y:.6} {z:.6}");
+            }
+        }
+        Command::Stream => {
+            let offset = mag.calibrate_offset(&mut delay)?.gauss();
+            let rate = options.config.output_rate();
+            mag.set_max_polls(stream_max_polls(rate));
+            let mut mag = mag.start_continuous()?;
+            let period = Duration::from_micros(rate.period_us().into());
+            let start = Instant::now();
+            let mut next = start;
+            let mut remaining = options.count;
+            while remaining != Some(0) {
+                // The measurement done flag stays set in continuous mode, so
+                // reading faster than the output data rate repeats samples
+                next += period;
+                thread::sleep(next.saturating_duration_since(Instant::now()));
+                let (x, y, z) = mag.magnetic_field()?.gauss();
+                let t = start.elapsed().as_secs_f64();
+                println!(
+                    "{t:.6} {:.6} {:.6} {:.6}",
+                    x - offset.0,
+                    y - offset.1,
+                    z - offset.2
+                );
+                remaining = remaining.map(|n| n - 1);
+            }
+        }
+        Command::Temp => {
+            println!("{:.1} °C", mag.temperature()?.degrees_celsius());
+        }
+        Command::Calibrate => {
+            let offset = mag.calibrate_offset(&mut delay)?;
+            let (x, y, z) = offset.gauss();
+            println!(
+                "offset: {} {} {} counts ({x:.6} {y:.6} {z:.6} G)",
+                offset.x(),
+                offset.y(),
+                offset.z()
+            );
+        }
+        Command::SelfTest => {
+            let report = mag.self_test(&mut delay, options.limits)?;
+            let (x, y, z) = report.delta_gauss();
+            let (px, py, pz) = report.axes_passed();
+            let verdict = |passed| if passed { "pass" } else { "FAIL" };
+            println!("x: {x:.4} G {}", verdict(px));
+            println!("y: {y:.4} G {}", verdict(py));
+            println!("z: {z:.4} G {}", verdict(pz));
+            return Ok(report.passed());
+        }
+        Command::Id | Command::DumpRegs => unreachable!(),
+    }
+    Ok(true)
+}
+
+fn report<E: Debug>(result: Result<bool, Error<E>>) -> ExitCode {
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {e:?}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match &options.bus {
+        Bus::I2c(path) => match I2cdev::new(path) {
+            Ok(i2c) => report(run(Mmc5983::new_with_i2c(i2c), &options)),
+            Err(e) => {
+                eprintln!("error: cannot open {path}: {e}");
+                ExitCode::FAILURE
+            }
+        },
+        Bus::Spi(path) => {
+            let spi = Spidev::open(path).and_then(|mut spi| {
+                let spi_options = SpidevOptions::new()
+                    .bits_per_word(8)
+                    .max_speed_hz(SPI_SPEED_HZ)
+                    .mode(SpiModeFlags::SPI_MODE_0)
+                    .build();
+                spi.configure(&spi_options)?;
+                Ok(spi)
+            });
+            match spi {
+                Ok(spi) => report(run(Mmc5983::new_with_spi(SpidevDevice(spi)), &options)),
+                Err(e) => {
+                    eprintln!("error: cannot open {path}: {e}");
+                    ExitCode::FAILURE
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Option<Options>, String> {
+        parse_args(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn picks_bandwidth_for_rate() {
+        let options = parse("--spi /dev/spidev1.1 stream --rate 1000 --count 5")
+            .unwrap()
+            .unwrap();
+        assert_eq!(options.command, Command::Stream);
+        assert_eq!(options.count, Some(5));
+        assert_eq!(options.config.bandwidth(), BandwidthMode::Hz800);
+
+        assert!(parse("--i2c /dev/i2c-1 --rate 1000 --bandwidth 400 read").is_err());
+        assert!(parse("--i2c /dev/i2c-1 --rate 30 read").is_err());
+        assert!(parse("--i2c /dev/i2c-1").is_err());
+        assert!(parse("read").is_err());
+        assert!(parse("--help").unwrap().is_none());
+    }
+
+    #[test]
+    fn stream_poll_budget_covers_one_period() {
+        assert_eq!(
+            stream_max_polls(MagOutputDataRate::Hz1000),
+            DEFAULT_MAX_POLLS
+        );
+        assert_eq!(stream_max_polls(MagOutputDataRate::Hz1), 100_000);
+    }
+}
@@ -0,0 +1,398 @@
+//! Hard-iron and soft-iron calibration
+//!
+//! Magnetic material and currents on the vehicle distort the measured field:
+//! a constant bias (hard iron) shifts it and a linear distortion (soft iron)
+//! stretches the sphere traced by a rotating sensor into an ellipsoid.
+//!
+//! [`IronCalibrator`] collects samples while the device is rotated through
+//! as many orientations as possible and fits an ellipsoid to them. The
+//! resulting [`IronCalibration`] maps every reading back onto a sphere:
+//!
+//! ```
+//! use mmc5983_rs::calibration::IronCalibrator;
+//! # let samples: [(f32, f32, f32); 0] = [];
+//!
+//! let mut calibrator = IronCalibrator::new();
+//! for sample in samples {
+//!     calibrator.add_gauss(sample);
+//! }
+//! if let Ok(calibration) = calibrator.fit() {
+//!     let corrected = calibration.apply_gauss((0.2, -0.1, 0.4));
+//! }
+//! ```
+//!
+//! Samples are accumulated into the normal equations of the fit, so no
+//! sample storage is needed.
+
+// Index loops read better than iterator chains for the matrix code below
+#![allow(clippy::needless_range_loop)]
+
+use crate::MagneticFieldI32;
+
+/// Number of ellipsoid parameters
+const PARAMS: usize = 9;
+
+/// Calibration fitting errors
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CalibrationError {
+    /// Fewer samples than ellipsoid parameters were collected
+    NotEnoughSamples,
+    /// The samples do not describe an ellipsoid, usually because the device
+    /// was not rotated through enough orientations
+    Degenerate,
+}
+
+/// Hard-iron bias and soft-iron correction matrix
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IronCalibration {
+    hard_iron: (f32, f32, f32),
+    soft_iron: [[f32; 3]; 3],
+}
+
+impl Default for IronCalibration {
+    fn default() -> Self {
+        Self {
+            hard_iron: (0.0, 0.0, 0.0),
+            soft_iron: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+        }
+    }
+}
+
+impl IronCalibration {
+    /// Create a calibration from a known hard-iron bias (in Gauss) and
+    /// soft-iron correction matrix.
+    pub const fn new(hard_iron: (f32, f32, f32), soft_iron: [[f32; 3]; 3]) -> Self {
+        Self {
+            hard_iron,
+            soft_iron,
+        }
+    }
+
+    /// Hard-iron bias in Gauss
+    pub const fn hard_iron(&self) -> (f32, f32, f32) {
+        self.hard_iron
+    }
+
+    /// Soft-iron correction matrix
+    pub const fn soft_iron(&self) -> [[f32; 3]; 3] {
+        self.soft_iron
+    }
+
+    /// Correct a field given in Gauss
+    pub fn apply_gauss(&self, (x, y, z): (f32, f32, f32)) -> (f32, f32, f32) {
+        let v = [
+            x - self.hard_iron.0,
+            y - self.hard_iron.1,
+            z - self.hard_iron.2,
+        ];
+        let m = &self.soft_iron;
+        let row = |r: &[f32; 3]| r[0] * v[0] + r[1] * v[1] + r[2] * v[2];
+        (row(&m[0]), row(&m[1]), row(&m[2]))
+    }
+
+    /// Correct a measured field, returning Gauss
+    pub fn apply(&self, field: &MagneticFieldI32) -> (f32, f32, f32) {
+        self.apply_gauss(field.gauss())
+    }
+}
+
+/// Collects samples and fits an ellipsoid to them
+///
+/// The fitted model is
+/// `a x² + b y² + c z² + 2d xy + 2e xz + 2f yz + 2g x + 2h y + 2i z = 1`,
+/// solved in the least-squares sense.
+#[derive(Debug, Clone)]
+pub struct IronCalibrator {
+    /// Upper triangle of `ΦᵀΦ`
+    ata: [[f64; PARAMS]; PARAMS],
+    /// `Φᵀ·1`
+    atb: [f64; PARAMS],
+    samples: u32,
+}
+
+impl Default for IronCalibrator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IronCalibrator {
+    /// Create an empty calibrator
+    pub const fn new() -> Self {
+        Self {
+            ata: [[0.0; PARAMS]; PARAMS],
+            atb: [0.0; PARAMS],
+            samples: 0,
+        }
+    }
+
+    /// Number of samples collected so far
+    pub const fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// Discard all collected samples
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Add a measured field
+    pub fn add_sample(&mut self, field: &MagneticFieldI32) {
+        self.add_gauss(field.gauss());
+    }
+
+    /// Add a field given in Gauss
+    pub fn add_gauss(&mut self, (x, y, z): (f32, f32, f32)) {
+        let (x, y, z) = (x as f64, y as f64, z as f64);
+        let row = [
+            x * x,
+            y * y,
+            z * z,
+            2.0 * x * y,
+            2.0 * x * z,
+            2.0 * y * z,
+            2.0 * x,
+            2.0 * y,
+            2.0 * z,
+        ];
+        for i in 0..PARAMS {
+            for j in i..PARAMS {
+                self.ata[i][j] += row[i] * row[j];
+            }
+            self.atb[i] += row[i];
+        }
+        self.samples = self.samples.saturating_add(1);
+    }
+
+    /// Fit an ellipsoid to the collected samples
+    pub fn fit(&self) -> Result<IronCalibration, CalibrationError> {
+        if (self.samples as usize) < PARAMS {
+            return Err(CalibrationError::NotEnoughSamples);
+        }
+
+        let mut ata = self.ata;
+        for i in 0..PARAMS {
+            for j in 0..i {
+                ata[i][j] = ata[j][i];
+            }
+        }
+        let p = solve(ata, self.atb).ok_or(CalibrationError::Degenerate)?;
+
+        let shape = [[p[0], p[3], p[4]], [p[3], p[1], p[5]], [p[4], p[5], p[2]]];
+        let linear = [p[6], p[7], p[8]];
+
+        // Centre of the ellipsoid: c = -M⁻¹v
+        let inverse = invert3(&shape).ok_or(CalibrationError::Degenerate)?;
+        let centre = mul3(&inverse, &linear).map(|c| -c);
+
+        // (x - c)ᵀ M (x - c) = 1 + cᵀ M c
+        let mc = mul3(&shape, &centre);
+        let k = 1.0 + centre[0] * mc[0] + centre[1] * mc[1] + centre[2] * mc[2];
+        if k <= 0.0 {
+            return Err(CalibrationError::Degenerate);
+        }
+        let shape = shape.map(|row| row.map(|v| v / k));
+
+        // Soft-iron matrix: square root of the normalised shape, scaled so that
+        // the corrected field keeps the mean radius of the ellipsoid.
+        let (values, vectors) = eigen3(shape);
+        if values.iter().any(|&v| v <= 0.0) {
+            return Err(CalibrationError::Degenerate);
+        }
+        let radius = 1.0 / libm::cbrt(libm::sqrt(values[0] * values[1] * values[2]));
+        let roots = values.map(|v| libm::sqrt(v) * radius);
+        let mut soft_iron = [[0.0f32; 3]; 3];
+        for (i, row) in soft_iron.iter_mut().enumerate() {
+            for (j, cell) in row.iter_mut().enumerate() {
+                *cell = (0..3)
+                    .map(|k| vectors[i][k] * roots[k] * vectors[j][k])
+                    .sum::<f64>() as f32;
+            }
+        }
+
+        Ok(IronCalibration {
+            hard_iron: (centre[0] as f32, centre[1] as f32, centre[2] as f32),
+            soft_iron,
+        })
+    }
+}
+
+/// Solve `a·x = b` by Gaussian elimination with partial pivoting
+fn solve(mut a: [[f64; PARAMS]; PARAMS], mut b: [f64; PARAMS]) -> Option<[f64; PARAMS]> {
+    let scale = a
+        .iter()
+        .flat_map(|row| row.iter())
+        .fold(0.0f64, |acc, v| acc.max(v.abs()));
+    if scale == 0.0 {
+        return None;
+    }
+
+    for col in 0..PARAMS {
+        let pivot = (col..PARAMS).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() <= scale * 1e-12 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        for row in col + 1..PARAMS {
+            let factor = a[row][col] / a[col][col];
+            for k in col..PARAMS {
+                a[row][k] -= factor * a[col][k];
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+
+    let mut x = [0.0; PARAMS];
+    for row in (0..PARAMS).rev() {
+        let sum: f64 = (row + 1..PARAMS).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - sum) / a[row][row];
+    }
+    Some(x)
+}
+
+fn mul3(m: &[[f64; 3]; 3], v: &[f64; 3]) -> [f64; 3] {
+    m.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
+}
+
+fn invert3(m: &[[f64; 3]; 3]) -> Option<[[f64; 3]; 3]> {
+    let cof =
+        |r0: usize, r1: usize, c0: usize, c1: usize| m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0];
+    let adj = [
+        [cof(1, 2, 1, 2), -cof(0, 2, 1, 2), cof(0, 1, 1, 2)],
+        [-cof(1, 2, 0, 2), cof(0, 2, 0, 2), -cof(0, 1, 0, 2)],
+        [cof(1, 2, 0, 1), -cof(0, 2, 0, 1), cof(0, 1, 0, 1)],
+    ];
+    let det = m[0][0] * adj[0][0] + m[0][1] * adj[1][0] + m[0][2] * adj[2][0];
+    if det.abs() < f64::EPSILON {
+        return None;
+    }
+    Some(adj.map(|row| row.map(|v| v / det)))
+}
+
+/// Eigen-decomposition of a symmetric 3x3 matrix using Jacobi rotations.
+///
+/// Returns the eigenvalues and a matrix holding the eigenvectors as columns.
+fn eigen3(mut a: [[f64; 3]; 3]) -> ([f64; 3], [[f64; 3]; 3]) {
+    let mut v = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    for _ in 0..50 {
+        let off = a[0][1] * a[0][1] + a[0][2] * a[0][2] + a[1][2] * a[1][2];
+        if off < 1e-30 {
+            break;
+        }
+        for (p, q) in [(0, 1), (0, 2), (1, 2)] {
+            if a[p][q].abs() < 1e-300 {
+                continue;
+            }
+            let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+            let t = theta.signum() / (theta.abs() + libm::sqrt(theta * theta + 1.0));
+            let c = 1.0 / libm::sqrt(t * t + 1.0);
+            let s = t * c;
+            for k in 0..3 {
+                let (akp, akq) = (a[k][p], a[k][q]);
+                a[k][p] = c * akp - s * akq;
+                a[k][q] = s * akp + c * akq;
+            }
+            for k in 0..3 {
+                let (apk, aqk) = (a[p][k], a[q][k]);
+                a[p][k] = c * apk - s * aqk;
+                a[q][k] = s * apk + c * aqk;
+            }
+            for row in v.iter_mut() {
+                let (vp, vq) = (row[p], row[q]);
+                row[p] = c * vp - s * vq;
+                row[q] = s * vp + c * vq;
+            }
+        }
+    }
+    ([a[0][0], a[1][1], a[2][2]], v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BIAS: (f32, f32, f32) = (0.12, -0.3, 0.05);
+    /// Distortion applied to the true field (symmetric, so a pure soft-iron effect)
+    const DISTORTION: [[f32; 3]; 3] = [[1.2, 0.1, 0.0], [0.1, 0.9, 0.05], [0.0, 0.05, 1.05]];
+    const FIELD: f32 = 0.5;
+
+    fn distorted(true_field: (f32, f32, f32)) -> (f32, f32, f32) {
+        let v = [true_field.0, true_field.1, true_field.2];
+        let row = |r: &[f32; 3]| r[0] * v[0] + r[1] * v[1] + r[2] * v[2];
+        (
+            row(&DISTORTION[0]) + BIAS.0,
+            row(&DISTORTION[1]) + BIAS.1,
+            row(&DISTORTION[2]) + BIAS.2,
+        )
+    }
+
+    fn rotate_through_sphere(mut f: impl FnMut((f32, f32, f32))) {
+        for i in 0..12 {
+            let theta = core::f32::consts::PI * (i as f32 + 0.5) / 12.0;
+            for j in 0..24 {
+                let phi = 2.0 * core::f32::consts::PI * j as f32 / 24.0;
+                f((
+                    FIELD * libm::sinf(theta) * libm::cosf(phi),
+                    FIELD * libm::sinf(theta) * libm::sinf(phi),
+                    FIELD * libm::cosf(theta),
+                ));
+            }
+        }
+    }
+
+    fn norm((x, y, z): (f32, f32, f32)) -> f32 {
+        libm::sqrtf(x * x + y * y + z * z)
+    }
+
+    #[test]
+    fn needs_enough_samples() {
+        let mut calibrator = IronCalibrator::new();
+        calibrator.add_gauss((0.1, 0.2, 0.3));
+        assert_eq!(calibrator.fit(), Err(CalibrationError::NotEnoughSamples));
+    }
+
+    #[test]
+    fn rejects_samples_from_a_single_orientation() {
+        let mut calibrator = IronCalibrator::new();
+        for _ in 0..20 {
+            calibrator.add_gauss((0.1, 0.2, 0.3));
+        }
+        assert_eq!(calibrator.fit(), Err(CalibrationError::Degenerate));
+    }
+
+    #[test]
+    fn identity_on_undistorted_sphere() {
+        let mut calibrator = IronCalibrator::new();
+        rotate_through_sphere(|v| calibrator.add_gauss(v));
+        let calibration = calibrator.fit().unwrap();
+        let (bx, by, bz) = calibration.hard_iron();
+        assert!(bx.abs() < 1e-4 && by.abs() < 1e-4 && bz.abs() < 1e-4);
+        for (i, row) in calibration.soft_iron().iter().enumerate() {
+            for (j, &v) in row.iter().enumerate() {
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!((v - expected).abs() < 1e-3, "{:?}", calibration);
+            }
+        }
+    }
+
+    #[test]
+    fn recovers_hard_and_soft_iron() {
+        let mut calibrator = IronCalibrator::new();
+        rotate_through_sphere(|v| calibrator.add_gauss(distorted(v)));
+        let calibration = calibrator.fit().unwrap();
+
+        let (bx, by, bz) = calibration.hard_iron();
+        assert!((bx - BIAS.0).abs() < 1e-3);
+        assert!((by - BIAS.1).abs() < 1e-3);
+        assert!((bz - BIAS.2).abs() < 1e-3);
+
+        // Corrected samples lie on a sphere
+        let mut radius = None;
+        rotate_through_sphere(|v| {
+            let r = norm(calibration.apply_gauss(distorted(v)));
+            let expected = *radius.get_or_insert(r);
+            assert!((r - expected).abs() < 1e-3 * expected);
+        });
+    }
+}
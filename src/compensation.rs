@@ -0,0 +1,275 @@
+//! Temperature compensation
+//!
+//! The bridge offset and the sensitivity of the sensor drift with
+//! temperature. [`TempCompensation`] models both as linear in °C around a
+//! reference temperature:
+//!
+//! `measured = true · (1 + gain_drift · ΔT) + offset + offset_drift · ΔT`
+//!
+//! [`TempCompensator`] fits the coefficients from SET/RESET measurement
+//! pairs logged over temperature, for example with
+//! [`Mmc5983::measure_set_reset`](crate::Mmc5983::measure_set_reset) and
+//! [`Mmc5983::temperature`](crate::Mmc5983::temperature) while the board
+//! warms up. The mean of a pair is the bridge offset, half their difference
+//! the field as seen with the current sensitivity, so the ambient field does
+//! not need to be known.
+//!
+//! ```
+//! use mmc5983_rs::compensation::TempCompensator;
+//! # let log: [(f32, (f32, f32, f32), (f32, f32, f32)); 0] = [];
+//!
+//! let mut compensator = TempCompensator::new(25.0);
+//! for (temperature, set, reset) in log {
+//!     compensator.add_gauss(temperature, set, reset);
+//! }
+//! if let Ok(compensation) = compensator.fit() {
+//!     let corrected = compensation.apply_gauss((0.2, -0.1, 0.4), 40.0);
+//! }
+//! ```
+
+use crate::calibration::CalibrationError;
+use crate::{MagneticFieldI32, Temperature};
+
+/// Half-difference below which an axis' gain drift is not fitted, in Gauss
+const MIN_GAIN_FIELD: f64 = 0.01;
+
+/// Per-axis offset and gain drift against temperature
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TempCompensation {
+    reference: f32,
+    offset: (f32, f32, f32),
+    offset_drift: (f32, f32, f32),
+    gain_drift: (f32, f32, f32),
+}
+
+impl TempCompensation {
+    /// Create a model from known coefficients.
+    ///
+    /// `offset` is the offset in Gauss at `reference` °C, `offset_drift` its
+    /// change in Gauss/°C and `gain_drift` the relative sensitivity change
+    /// per °C.
+    pub const fn new(
+        reference: f32,
+        offset: (f32, f32, f32),
+        offset_drift: (f32, f32, f32),
+        gain_drift: (f32, f32, f32),
+    ) -> Self {
+        Self {
+            reference,
+            offset,
+            offset_drift,
+            gain_drift,
+        }
+    }
+
+    /// Reference temperature in °C
+    pub const fn reference(&self) -> f32 {
+        self.reference
+    }
+
+    /// Offset at the reference temperature in Gauss
+    pub const fn offset(&self) -> (f32, f32, f32) {
+        self.offset
+    }
+
+    /// Offset drift in Gauss/°C
+    pub const fn offset_drift(&self) -> (f32, f32, f32) {
+        self.offset_drift
+    }
+
+    /// Relative sensitivity drift per °C
+    pub const fn gain_drift(&self) -> (f32, f32, f32) {
+        self.gain_drift
+    }
+
+    /// Correct a field given in Gauss, measured at `temperature` °C
+    pub fn apply_gauss(&self, (x, y, z): (f32, f32, f32), temperature: f32) -> (f32, f32, f32) {
+        let dt = temperature - self.reference;
+        let axis = |v: f32, offset: f32, offset_drift: f32, gain_drift: f32| {
+            (v - offset - offset_drift * dt) / (1.0 + gain_drift * dt)
+        };
+        (
+            axis(x, self.offset.0, self.offset_drift.0, self.gain_drift.0),
+            axis(y, self.offset.1, self.offset_drift.1, self.gain_drift.1),
+            axis(z, self.offset.2, self.offset_drift.2, self.gain_drift.2),
+        )
+    }
+
+    /// Correct a measured field, returning Gauss
+    pub fn apply(&self, field: &MagneticFieldI32, temperature: Temperature) -> (f32, f32, f32) {
+        self.apply_gauss(field.gauss(), temperature.degrees_celsius())
+    }
+}
+
+/// Least-squares line through `(ΔT, value)` points
+#[derive(Debug, Default, Clone, Copy)]
+struct Line {
+    t: f64,
+    tt: f64,
+    v: f64,
+    tv: f64,
+}
+
+impl Line {
+    fn add(&mut self, t: f64, v: f64) {
+        self.t += t;
+        self.tt += t * t;
+        self.v += v;
+        self.tv += t * v;
+    }
+
+    /// Intercept and slope
+    fn fit(&self, n: f64) -> (f64, f64) {
+        let slope = (n * self.tv - self.t * self.v) / (n * self.tt - self.t * self.t);
+        ((self.v - slope * self.t) / n, slope)
+    }
+}
+
+/// Collects SET/RESET pairs over temperature and fits a [`TempCompensation`]
+#[derive(Debug, Clone)]
+pub struct TempCompensator {
+    reference: f32,
+    offset: [Line; 3],
+    field: [Line; 3],
+    samples: u32,
+}
+
+impl TempCompensator {
+    /// Create an empty compensator fitting around `reference` °C
+    pub fn new(reference: f32) -> Self {
+        Self {
+            reference,
+            offset: [Line::default(); 3],
+            field: [Line::default(); 3],
+            samples: 0,
+        }
+    }
+
+    /// Number of pairs collected so far
+    pub const fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// Discard all collected pairs
+    pub fn reset(&mut self) {
+        *self = Self::new(self.reference);
+    }
+
+    /// Add a SET and a RESET measurement taken at `temperature`
+    pub fn add_sample(
+        &mut self,
+        temperature: Temperature,
+        set: &MagneticFieldI32,
+        reset: &MagneticFieldI32,
+    ) {
+        self.add_gauss(temperature.degrees_celsius(), set.gauss(), reset.gauss());
+    }
+
+    /// Add a SET and a RESET measurement in Gauss taken at `temperature` °C
+    pub fn add_gauss(&mut self, temperature: f32, set: (f32, f32, f32), reset: (f32, f32, f32)) {
+        let dt = (temperature - self.reference) as f64;
+        let set = [set.0, set.1, set.2];
+        let reset = [reset.0, reset.1, reset.2];
+        for axis in 0..3 {
+            let (s, r) = (set[axis] as f64, reset[axis] as f64);
+            self.offset[axis].add(dt, (s + r) / 2.0);
+            self.field[axis].add(dt, (s - r) / 2.0);
+        }
+        self.samples = self.samples.saturating_add(1);
+    }
+
+    /// Fit the compensation coefficients.
+    ///
+    /// Axes seeing less than 10 mG of ambient field keep a zero gain drift,
+    /// since their sensitivity change cannot be told apart from noise.
+    pub fn fit(&self) -> Result<TempCompensation, CalibrationError> {
+        if self.samples < 2 {
+            return Err(CalibrationError::NotEnoughSamples);
+        }
+        let n = self.samples as f64;
+        let spread = n * self.offset[0].tt - self.offset[0].t * self.offset[0].t;
+        if spread <= f64::EPSILON * n * self.offset[0].tt {
+            return Err(CalibrationError::Degenerate);
+        }
+
+        let mut offset = [0.0; 3];
+        let mut offset_drift = [0.0; 3];
+        let mut gain_drift = [0.0; 3];
+        for axis in 0..3 {
+            let (o, od) = self.offset[axis].fit(n);
+            let (f, fd) = self.field[axis].fit(n);
+            offset[axis] = o as f32;
+            offset_drift[axis] = od as f32;
+            if f.abs() >= MIN_GAIN_FIELD {
+                gain_drift[axis] = (fd / f) as f32;
+            }
+        }
+        let tuple = |v: [f32; 3]| (v[0], v[1], v[2]);
+        Ok(TempCompensation::new(
+            self.reference,
+            tuple(offset),
+            tuple(offset_drift),
+            tuple(gain_drift),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: (f32, f32, f32), expected: (f32, f32, f32)) {
+        let diff = [
+            actual.0 - expected.0,
+            actual.1 - expected.1,
+            actual.2 - expected.2,
+        ];
+        assert!(
+            diff.iter().all(|d| d.abs() < 1e-4),
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn needs_temperature_spread() {
+        let mut compensator = TempCompensator::new(25.0);
+        compensator.add_gauss(30.0, (0.1, 0.2, 0.3), (-0.1, -0.2, -0.3));
+        assert_eq!(compensator.fit(), Err(CalibrationError::NotEnoughSamples));
+        compensator.add_gauss(30.0, (0.1, 0.2, 0.3), (-0.1, -0.2, -0.3));
+        assert_eq!(compensator.fit(), Err(CalibrationError::Degenerate));
+    }
+
+    #[test]
+    fn recovers_drift() {
+        let truth = TempCompensation::new(
+            25.0,
+            (0.01, -0.02, 0.03),
+            (0.001, 0.0, -0.0005),
+            (-0.002, 0.001, 0.0),
+        );
+        let field = (0.2, -0.15, 0.0);
+        let measure = |t: f32, (x, y, z): (f32, f32, f32)| {
+            let dt = t - 25.0;
+            let axis = |v: f32, o: f32, od: f32, g: f32| v * (1.0 + g * dt) + o + od * dt;
+            (
+                axis(x, 0.01, 0.001, -0.002),
+                axis(y, -0.02, 0.0, 0.001),
+                axis(z, 0.03, -0.0005, 0.0),
+            )
+        };
+
+        let mut compensator = TempCompensator::new(25.0);
+        for t in [0.0, 10.0, 25.0, 40.0, 60.0] {
+            let (x, y, z) = field;
+            compensator.add_gauss(t, measure(t, field), measure(t, (-x, -y, -z)));
+        }
+        let fitted = compensator.fit().unwrap();
+        assert_close(fitted.offset(), truth.offset());
+        assert_close(fitted.offset_drift(), truth.offset_drift());
+        assert_close(fitted.gain_drift(), truth.gain_drift());
+
+        for t in [5.0, 50.0] {
+            assert_close(fitted.apply_gauss(measure(t, field), t), field);
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{BandwidthMode, Channels, MagOutputDataRate, Resolution, SetResetPeriod};
+
+/// Invalid device configurations
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConfigError {
+    /// The output data rate needs a higher bandwidth than configured
+    BandwidthTooLow {
+        /// Requested output data rate
+        rate: MagOutputDataRate,
+        /// Configured bandwidth
+        bandwidth: BandwidthMode,
+        /// Lowest bandwidth supporting `rate`
+        required: BandwidthMode,
+    },
+}
+
+/// Device configuration applied by [`Mmc5983::init_with`](crate::Mmc5983::init_with)
+///
+/// ```
+/// use mmc5983_rs::{BandwidthMode, Config, MagOutputDataRate, SetResetPeriod};
+///
+/// let config = Config::new()
+///     .with_bandwidth(BandwidthMode::Hz800)
+///     .with_output_rate(MagOutputDataRate::Hz1000)
+///     .with_set_period(Some(SetResetPeriod::Every100));
+/// assert!(config.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    bandwidth: BandwidthMode,
+    resolution: Resolution,
+    output_rate: MagOutputDataRate,
+    set_period: Option<SetResetPeriod>,
+    interrupt: bool,
+    channels: Channels,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Config {
+    /// Configuration used by `init`: 100Hz bandwidth, 18-bit output, 100Hz
+    /// continuous rate without periodic SET, measurement done interrupt
+    /// enabled and all channels measured
+    pub const fn new() -> Self {
+        Self {
+            bandwidth: BandwidthMode::Hz100,
+            resolution: Resolution::Bits18,
+            output_rate: MagOutputDataRate::Hz100,
+            set_period: None,
+            interrupt: true,
+            channels: Channels::Xyz,
+        }
+    }
+
+    /// Set the measurement bandwidth
+    pub const fn with_bandwidth(self, bandwidth: BandwidthMode) -> Self {
+        Self { bandwidth, ..self }
+    }
+
+    /// Set the magnetic field output resolution
+    pub const fn with_resolution(self, resolution: Resolution) -> Self {
+        Self { resolution, ..self }
+    }
+
+    /// Set the output data rate used by
+    /// [`start_continuous`](crate::Mmc5983::start_continuous)
+    pub const fn with_output_rate(self, output_rate: MagOutputDataRate) -> Self {
+        Self {
+            output_rate,
+            ..self
+        }
+    }
+
+    /// Set the period of automatic SET operations in continuous mode, `None`
+    /// to disable them
+    pub const fn with_set_period(self, set_period: Option<SetResetPeriod>) -> Self {
+        Self { set_period, ..self }
+    }
+
+    /// Enable or disable the measurement done interrupt
+    pub const fn with_interrupt(self, interrupt: bool) -> Self {
+        Self { interrupt, ..self }
+    }
+
+    /// Select the measured channels
+    pub const fn with_channels(self, channels: Channels) -> Self {
+        Self { channels, ..self }
+    }
+
+    /// Measurement bandwidth
+    pub const fn bandwidth(&self) -> BandwidthMode {
+        self.bandwidth
+    }
+
+    /// Magnetic field output resolution
+    pub const fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    /// Continuous mode output data rate
+    pub const fn output_rate(&self) -> MagOutputDataRate {
+        self.output_rate
+    }
+
+    /// Period of automatic SET operations in continuous mode
+    pub const fn set_period(&self) -> Option<SetResetPeriod> {
+        self.set_period
+    }
+
+    /// Whether the measurement done interrupt is enabled
+    pub const fn interrupt(&self) -> bool {
+        self.interrupt
+    }
+
+    /// Measured channels
+    pub const fn channels(&self) -> Channels {
+        self.channels
+    }
+
+    /// Check that the settings can be used together
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        check_rate(self.output_rate, self.bandwidth)
+    }
+}
+
+/// Check that continuous mode can run at `rate` with `bandwidth`
+pub(crate) fn check_rate(
+    rate: MagOutputDataRate,
+    bandwidth: BandwidthMode,
+) -> Result<(), ConfigError> {
+    if bandwidth.supports(rate) {
+        Ok(())
+    } else {
+        Err(ConfigError::BandwidthTooLow {
+            rate,
+            bandwidth,
+            required: BandwidthMode::for_output_rate(rate),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_rate_above_bandwidth() {
+        assert!(Config::new().validate().is_ok());
+
+        let config = Config::new().with_output_rate(MagOutputDataRate::Hz1000);
+        assert_eq!(
+            config.validate(),
+            Err(ConfigError::BandwidthTooLow {
+                rate: MagOutputDataRate::Hz1000,
+                bandwidth: BandwidthMode::Hz100,
+                required: BandwidthMode::Hz800,
+            })
+        );
+        let config = config.with_bandwidth(BandwidthMode::Hz400);
+        assert!(config.validate().is_err());
+        let config = config.with_bandwidth(BandwidthMode::Hz800);
+        assert!(config.validate().is_ok());
+
+        let config = Config::new().with_output_rate(MagOutputDataRate::Hz200);
+        assert!(config.validate().is_err());
+        let config = config.with_bandwidth(BandwidthMode::Hz200);
+        assert!(config.validate().is_ok());
+    }
+}
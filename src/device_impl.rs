@@ -1,20 +1,51 @@
 use maybe_async_cfg::maybe;
 
-#[cfg(not(feature = "async"))]
-use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::Error as _;
+use embedded_hal::{delay::DelayNs, digital::InputPin as IntPin};
 #[cfg(feature = "async")]
-use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::{delay::DelayNs as DelayNsAsync, digital::Wait as IntPinAsync};
+
+#[cfg(feature = "async")]
+use crate::{
+    interface::{ReadDataAsync, WriteDataAsync},
+    Mmc5983Async,
+};
 
 use crate::{
-    interface::{I2cInterface, ReadData, SpiInterface, WriteData},
+    config::check_rate,
+    interface::{
+        I2cInterface, ReadData, ReplayInterface, Spi3WireInterface, SpiInterface, WriteData,
+    },
     mode,
+    record::{Recording, Snapshot, SNAPSHOT_REGISTERS},
     register_address::{
-        InternalControl0, InternalControl1, InternalControl2, InternalControl3, ProductId1, Status,
+        InternalControl0, InternalControl1, InternalControl2, InternalControl3, ProductId1,
+        RegRead, Xout0,
     },
-    BandwidthMode, Error, MagneticField, Mmc5983, PhantomData, ProductId, Status as DeviceStatus,
-    Temperature,
+    types::{OffsetTracking, StatusFlags},
+    BandwidthMode, Channels, Config, Error, MagneticField, MagneticFieldI32, Mmc5983, Offset,
+    PhantomData, ProductId, RegisterDump, Resolution, SelfTestLimits, SelfTestReport,
+    Status as DeviceStatus, Temperature, DEFAULT_MAX_POLLS,
 };
 
+/// Time between two reads of the INT pin by the blocking driver
+const INT_POLL_INTERVAL_US: u32 = 100;
+
+/// Time the chip needs after a software reset before it accepts
+/// configuration writes again
+const SW_RESET_TIME_MS: u32 = 10;
+
+#[maybe(
+    idents(
+        Mmc5983(sync),
+        ReadData(sync),
+        WriteData(sync),
+        DelayNs(sync),
+        IntPin(sync)
+    ),
+    sync(keep_self),
+    async(feature = "async", keep_self)
+)]
 impl<I2C> Mmc5983<I2cInterface<I2C>, mode::OneShot> {
     /// Create new instance of the MMC5983 device communicating through I2C.
     pub fn new_with_i2c(i2c: I2C) -> Self {
@@ -24,16 +55,117 @@ impl<I2C> Mmc5983<I2cInterface<I2C>, mode::OneShot> {
             ctrl_reg1: InternalControl1::default(),
             ctrl_reg2: InternalControl2::default(),
             ctrl_reg3: InternalControl3::default(),
-            offset: MagneticField {
-                x: 131072,
-                y: 131072,
-                z: 131072,
-            },
+            offset: Offset::default(),
+            max_polls: DEFAULT_MAX_POLLS,
+            resolution: Resolution::Bits18,
+            tracking: OffsetTracking::new(),
+            meas_pending: false,
+            _mode: PhantomData,
+        }
+    }
+}
+
+#[maybe(
+    idents(
+        Mmc5983(sync),
+        ReadData(sync),
+        WriteData(sync),
+        DelayNs(sync),
+        IntPin(sync)
+    ),
+    sync(keep_self),
+    async(feature = "async", keep_self)
+)]
+impl<DI, MODE> Mmc5983<DI, MODE> {
+    /// Set the maximum number of status reads performed while waiting for a
+    /// measurement to complete before returning [`Error::Timeout`].
+    ///
+    /// Defaults to [`DEFAULT_MAX_POLLS`]. Continuous mode at low output data
+    /// rates on a fast bus may need a larger budget.
+    pub fn set_max_polls(&mut self, polls: u32) {
+        self.max_polls = polls;
+    }
+
+    /// Current poll budget
+    pub fn max_polls(&self) -> u32 {
+        self.max_polls
+    }
+
+    /// Select the magnetic field output resolution.
+    ///
+    /// 16-bit reads skip the XYZout2 register, which shortens every transfer
+    /// for high output data rates on slow buses.
+    pub fn set_resolution(&mut self, resolution: Resolution) {
+        self.resolution = resolution;
+    }
+
+    /// Current magnetic field output resolution
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    /// Whether automatic SET/RESET on each measurement is enabled
+    pub fn auto_sr(&self) -> bool {
+        self.ctrl_reg0.contains(InternalControl0::AUTO_SR)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<DI, MODE> Mmc5983<DI, MODE> {
+    /// Convert into the async driver.
+    ///
+    /// The interface, the cached register configuration and the offset are
+    /// kept, so the device does not need to be initialized again. The bus
+    /// must also implement the `embedded-hal-async` traits.
+    pub fn into_async(self) -> Mmc5983Async<DI, MODE> {
+        Mmc5983Async {
+            iface: self.iface,
+            ctrl_reg0: self.ctrl_reg0,
+            ctrl_reg1: self.ctrl_reg1,
+            ctrl_reg2: self.ctrl_reg2,
+            ctrl_reg3: self.ctrl_reg3,
+            offset: self.offset,
+            max_polls: self.max_polls,
+            resolution: self.resolution,
+            tracking: self.tracking,
+            meas_pending: self.meas_pending,
+            _mode: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<DI, MODE> Mmc5983Async<DI, MODE> {
+    /// Convert into the blocking driver, keeping the interface, the cached
+    /// register configuration and the offset.
+    pub fn into_blocking(self) -> Mmc5983<DI, MODE> {
+        Mmc5983 {
+            iface: self.iface,
+            ctrl_reg0: self.ctrl_reg0,
+            ctrl_reg1: self.ctrl_reg1,
+            ctrl_reg2: self.ctrl_reg2,
+            ctrl_reg3: self.ctrl_reg3,
+            offset: self.offset,
+            max_polls: self.max_polls,
+            resolution: self.resolution,
+            tracking: self.tracking,
+            meas_pending: self.meas_pending,
             _mode: PhantomData,
         }
     }
 }
 
+#[maybe(
+    idents(
+        Mmc5983(sync),
+        ReadData(sync),
+        WriteData(sync),
+        DelayNs(sync),
+        IntPin(sync)
+    ),
+    sync(keep_self),
+    async(feature = "async", keep_self)
+)]
 impl<I2C, MODE> Mmc5983<I2cInterface<I2C>, MODE> {
     /// Destroy driver instance, return I2C bus.
     pub fn destroy(self) -> I2C {
@@ -41,6 +173,17 @@ impl<I2C, MODE> Mmc5983<I2cInterface<I2C>, MODE> {
     }
 }
 
+#[maybe(
+    idents(
+        Mmc5983(sync),
+        ReadData(sync),
+        WriteData(sync),
+        DelayNs(sync),
+        IntPin(sync)
+    ),
+    sync(keep_self),
+    async(feature = "async", keep_self)
+)]
 impl<SPI> Mmc5983<SpiInterface<SPI>, mode::OneShot> {
     /// Create new instance of the MMC5983 device communicating through SPI.
     pub fn new_with_spi(spi: SPI) -> Self {
@@ -50,16 +193,27 @@ impl<SPI> Mmc5983<SpiInterface<SPI>, mode::OneShot> {
             ctrl_reg1: InternalControl1::default(),
             ctrl_reg2: InternalControl2::default(),
             ctrl_reg3: InternalControl3::default(),
-            offset: MagneticField {
-                x: 131072,
-                y: 131072,
-                z: 131072,
-            },
+            offset: Offset::default(),
+            max_polls: DEFAULT_MAX_POLLS,
+            resolution: Resolution::Bits18,
+            tracking: OffsetTracking::new(),
+            meas_pending: false,
             _mode: PhantomData,
         }
     }
 }
 
+#[maybe(
+    idents(
+        Mmc5983(sync),
+        ReadData(sync),
+        WriteData(sync),
+        DelayNs(sync),
+        IntPin(sync)
+    ),
+    sync(keep_self),
+    async(feature = "async", keep_self)
+)]
 impl<SPI, MODE> Mmc5983<SpiInterface<SPI>, MODE> {
     /// Destroy driver instance, return SPI bus.
     pub fn destroy(self) -> SPI {
@@ -68,43 +222,220 @@ impl<SPI, MODE> Mmc5983<SpiInterface<SPI>, MODE> {
 }
 
 #[maybe(
-    sync(cfg(not(feature = "async")), keep_self,),
-    async(cfg(feature = "async"), keep_self,)
+    idents(
+        Mmc5983(sync),
+        ReadData(sync),
+        WriteData(sync),
+        DelayNs(sync),
+        IntPin(sync)
+    ),
+    sync(keep_self),
+    async(feature = "async", keep_self)
+)]
+impl<SPI> Mmc5983<Spi3WireInterface<SPI>, mode::OneShot> {
+    /// Create new instance of the MMC5983 device communicating through 3-wire
+    /// SPI.
+    ///
+    /// `init` switches the chip into 3-wire mode before reading from it.
+    pub fn new_with_spi_3wire(spi: SPI) -> Self {
+        Mmc5983 {
+            iface: Spi3WireInterface { spi },
+            ctrl_reg0: InternalControl0::default(),
+            ctrl_reg1: InternalControl1::default(),
+            ctrl_reg2: InternalControl2::default(),
+            ctrl_reg3: InternalControl3::SPI_3W,
+            offset: Offset::default(),
+            max_polls: DEFAULT_MAX_POLLS,
+            resolution: Resolution::Bits18,
+            tracking: OffsetTracking::new(),
+            meas_pending: false,
+            _mode: PhantomData,
+        }
+    }
+}
+
+#[maybe(
+    idents(
+        Mmc5983(sync),
+        ReadData(sync),
+        WriteData(sync),
+        DelayNs(sync),
+        IntPin(sync)
+    ),
+    sync(keep_self),
+    async(feature = "async", keep_self)
+)]
+impl<SPI, MODE> Mmc5983<Spi3WireInterface<SPI>, MODE> {
+    /// Destroy driver instance, return SPI bus.
+    pub fn destroy(self) -> SPI {
+        self.iface.spi
+    }
+}
+
+#[maybe(
+    idents(
+        Mmc5983(sync),
+        ReadData(sync),
+        WriteData(sync),
+        DelayNs(sync),
+        IntPin(sync)
+    ),
+    sync(keep_self),
+    async(feature = "async", keep_self)
+)]
+impl<'a> Mmc5983<ReplayInterface<'a>, mode::OneShot> {
+    /// Create new instance of the MMC5983 device replaying `recording`
+    /// instead of communicating with a sensor.
+    ///
+    /// See [`ReplayInterface`] for how the snapshots are consumed.
+    pub fn new_with_replay(recording: Recording<'a>) -> Self {
+        Mmc5983 {
+            iface: ReplayInterface::new(recording),
+            ctrl_reg0: InternalControl0::default(),
+            ctrl_reg1: InternalControl1::default(),
+            ctrl_reg2: InternalControl2::default(),
+            ctrl_reg3: InternalControl3::default(),
+            offset: Offset::default(),
+            max_polls: DEFAULT_MAX_POLLS,
+            resolution: Resolution::Bits18,
+            tracking: OffsetTracking::new(),
+            meas_pending: false,
+            _mode: PhantomData,
+        }
+    }
+}
+
+#[maybe(
+    idents(
+        Mmc5983(sync),
+        ReadData(sync),
+        WriteData(sync),
+        DelayNs(sync),
+        IntPin(sync)
+    ),
+    sync(keep_self),
+    async(feature = "async", keep_self)
+)]
+impl<'a, MODE> Mmc5983<ReplayInterface<'a>, MODE> {
+    /// Snapshots not replayed yet
+    pub fn remaining(&self) -> usize {
+        self.iface.remaining()
+    }
+}
+
+#[maybe(
+    idents(
+        Mmc5983(sync),
+        ReadData(sync),
+        WriteData(sync),
+        DelayNs(sync),
+        IntPin(sync)
+    ),
+    sync(keep_self),
+    async(feature = "async", keep_self)
 )]
 impl<DI, CommE, MODE> Mmc5983<DI, MODE>
 where
     DI: ReadData<Error = Error<CommE>> + WriteData<Error = Error<CommE>>,
 {
-    /// Initialize the device
+    /// Initialize the device with the default [`Config`].
+    ///
+    /// See [`init_with`](Self::init_with) about the software reset time.
     pub async fn init(&mut self) -> Result<(), Error<CommE>> {
+        self.init_with(Config::default()).await
+    }
+
+    /// Initialize the device and apply `config`.
+    ///
+    /// The configuration is validated before the device is accessed. The
+    /// output data rate and SET period take effect when continuous mode is
+    /// entered with [`start_continuous`](Mmc5983::start_continuous).
+    ///
+    /// The chip needs 10 ms after its software reset, but this writes the
+    /// configuration right away. Use [`init_with_delay`](Self::init_with_delay)
+    /// unless the bus is slow enough to cover that time.
+    pub async fn init_with(&mut self, config: Config) -> Result<(), Error<CommE>> {
+        config.validate().map_err(Error::Config)?;
+        self.check_and_reset().await?;
+        self.configure(config).await
+    }
+
+    /// Initialize the device and apply `config` like
+    /// [`init_with`](Self::init_with), waiting with `delay` for the software
+    /// reset to complete before the configuration is written.
+    pub async fn init_with_delay<D: DelayNs>(
+        &mut self,
+        config: Config,
+        delay: &mut D,
+    ) -> Result<(), Error<CommE>> {
+        config.validate().map_err(Error::Config)?;
+        self.check_and_reset().await?;
+        delay.delay_ms(SW_RESET_TIME_MS).await;
+        self.configure(config).await
+    }
+
+    /// Check the product ID and trigger a software reset
+    async fn check_and_reset(&mut self) -> Result<(), Error<CommE>> {
+        self.write_spi_mode().await?;
         let product_id = self.product_id().await?;
         if !product_id.is_correct() {
             return Err(Error::InvalidId(product_id));
         }
-        // Software reset
-        self.software_reset().await?;
+        self.software_reset().await
+    }
+
+    /// Write `config` to a device that was just reset
+    async fn configure(&mut self, config: Config) -> Result<(), Error<CommE>> {
+        self.write_spi_mode().await?;
         // Read OTP
         self.read_otp().await?;
         // Enable interrupt on measurement done
-        self.enable_meas_done_interrupt().await?;
-        // Set default bandwidth (100Hz)
-        self.set_bandwidth(BandwidthMode::Hz100).await
+        if config.interrupt() {
+            self.enable_meas_done_interrupt().await?;
+        }
+
+        let reg = self
+            .ctrl_reg1
+            .with_bandwidth(config.bandwidth())
+            .with_channels(config.channels());
+        self.iface.write_register(reg).await?;
+        self.ctrl_reg1 = reg;
+
+        let mut reg = self.ctrl_reg2.with_output_rate(config.output_rate());
+        if let Some(period) = config.set_period() {
+            reg = reg.with_set_period(period) | InternalControl2::EN_PRD_SET;
+        }
+        self.iface.write_register(reg).await?;
+        self.ctrl_reg2 = reg;
+
+        self.resolution = config.resolution();
+        Ok(())
     }
 
     /// Software reset
     async fn software_reset(&mut self) -> Result<(), Error<CommE>> {
         let reg = self.ctrl_reg1 | InternalControl1::SW_RST;
         self.iface.write_register(reg).await?;
+        self.ctrl_reg0 = InternalControl0::default();
         self.ctrl_reg1 = InternalControl1::default();
+        self.ctrl_reg2 = InternalControl2::default();
+        self.ctrl_reg3 &= InternalControl3::SPI_3W;
+        Ok(())
+    }
+
+    /// Switch the chip to 3-wire SPI if the interface needs it. The chip
+    /// starts in 4-wire mode, also after a software reset.
+    async fn write_spi_mode(&mut self) -> Result<(), Error<CommE>> {
+        if self.ctrl_reg3.contains(InternalControl3::SPI_3W) {
+            self.iface.write_register(self.ctrl_reg3).await?;
+        }
         Ok(())
     }
 
     /// Read OTP memory
     async fn read_otp(&mut self) -> Result<(), Error<CommE>> {
         let reg = self.ctrl_reg0 | InternalControl0::OTP_READ;
-        self.iface.write_register(reg).await?;
-        self.ctrl_reg0 = reg;
-        Ok(())
+        self.iface.write_register(reg).await
     }
 
     /// Enable measurement done interrupt
@@ -115,14 +446,37 @@ where
         Ok(())
     }
 
-    /// Set measurement bandwidth
+    /// Set measurement bandwidth.
+    ///
+    /// In continuous mode, bandwidths too low for the current output data
+    /// rate are refused with
+    /// [`ConfigError::BandwidthTooLow`](crate::ConfigError::BandwidthTooLow).
     pub async fn set_bandwidth(&mut self, bw: BandwidthMode) -> Result<(), Error<CommE>> {
+        if self.ctrl_reg2.contains(InternalControl2::CMM_EN) {
+            check_rate(self.ctrl_reg2.output_rate(), bw).map_err(Error::Config)?;
+        }
         let reg = self.ctrl_reg1.with_bandwidth(bw);
         self.iface.write_register(reg).await?;
         self.ctrl_reg1 = reg;
         Ok(())
     }
 
+    /// Select the measured channels.
+    ///
+    /// Inhibiting channels shortens each measurement. Inhibited channels are
+    /// reported as absent by [`MagneticFieldI32::checked_counts`].
+    pub async fn set_channels(&mut self, channels: Channels) -> Result<(), Error<CommE>> {
+        let reg = self.ctrl_reg1.with_channels(channels);
+        self.iface.write_register(reg).await?;
+        self.ctrl_reg1 = reg;
+        Ok(())
+    }
+
+    /// Currently measured channels
+    pub fn channels(&self) -> Channels {
+        self.ctrl_reg1.channels()
+    }
+
     /// Perform SET operation (magnetize sensor in positive direction)
     pub async fn set<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<CommE>> {
         let reg = self.ctrl_reg0 | InternalControl0::SET;
@@ -141,32 +495,235 @@ where
         Ok(())
     }
 
-    /// Perform SET operation (magnetize sensor in positive direction)
+    /// Enable automatic SET/RESET (`AUTO_SR`).
+    ///
+    /// The chip then performs SET/RESET on its own for each measurement, in
+    /// one-shot as well as in continuous mode. In continuous mode the
+    /// periodic SET of [`enable_auto_set_reset`](Mmc5983::enable_auto_set_reset)
+    /// additionally controls how often this happens.
+    pub async fn enable_auto_sr(&mut self) -> Result<(), Error<CommE>> {
+        let reg = self.ctrl_reg0 | InternalControl0::AUTO_SR;
+        self.iface.write_register(reg).await?;
+        self.ctrl_reg0 = reg;
+        Ok(())
+    }
+
+    /// Disable automatic SET/RESET (`AUTO_SR`)
+    pub async fn disable_auto_sr(&mut self) -> Result<(), Error<CommE>> {
+        let reg = self.ctrl_reg0.difference(InternalControl0::AUTO_SR);
+        self.iface.write_register(reg).await?;
+        self.ctrl_reg0 = reg;
+        Ok(())
+    }
+
+    /// Enable the positive self-test current (`ST_ENP`).
+    ///
+    /// Following measurements include an extra field generated on chip until
+    /// [`disable_self_test`](Self::disable_self_test) is called.
     pub async fn set_extra<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<CommE>> {
-        let reg = self.ctrl_reg3 | InternalControl3::ST_ENP;
+        let reg = self
+            .ctrl_reg3
+            .difference(InternalControl3::ST_ENM)
+            .union(InternalControl3::ST_ENP);
         self.iface.write_register(reg).await?;
-        delay.delay_ns(500).await; // SET pulse is 500ns
-        self.ctrl_reg0 = self.ctrl_reg0.difference(InternalControl0::SET);
+        self.ctrl_reg3 = reg;
+        delay.delay_ns(1000).await;
         Ok(())
     }
 
-    /// Perform SET operation (magnetize sensor in positive direction)
+    /// Enable the negative self-test current (`ST_ENM`).
+    ///
+    /// Following measurements include an extra field generated on chip until
+    /// [`disable_self_test`](Self::disable_self_test) is called.
     pub async fn reset_extra<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<CommE>> {
-        let reg = self.ctrl_reg3 | InternalControl3::ST_ENM;
+        let reg = self
+            .ctrl_reg3
+            .difference(InternalControl3::ST_ENP)
+            .union(InternalControl3::ST_ENM);
         self.iface.write_register(reg).await?;
-        delay.delay_ns(500).await; // SET pulse is 500ns
-        self.ctrl_reg0 = self.ctrl_reg0.difference(InternalControl0::SET);
+        self.ctrl_reg3 = reg;
+        delay.delay_ns(1000).await;
         Ok(())
     }
 
+    /// Disable both self-test currents
+    pub async fn disable_self_test(&mut self) -> Result<(), Error<CommE>> {
+        let reg = self
+            .ctrl_reg3
+            .difference(InternalControl3::ST_ENP | InternalControl3::ST_ENM);
+        self.iface.write_register(reg).await?;
+        self.ctrl_reg3 = reg;
+        Ok(())
+    }
+
+    /// Run the built-in self-test.
+    ///
+    /// After a SET operation, one measurement is taken with the positive and
+    /// one with the negative self-test current. Half their difference is the
+    /// field produced by the self-test coil on each axis, which is checked
+    /// against `limits`. The self-test current is disabled afterwards, also
+    /// when a measurement fails.
+    pub async fn self_test<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        limits: SelfTestLimits,
+    ) -> Result<SelfTestReport, Error<CommE>> {
+        self.set(delay).await?;
+        let result = self.self_test_fields(delay).await;
+        self.disable_self_test().await?;
+        let (plus, minus) = result?;
+
+        let (px, py, pz) = plus.gauss();
+        let (mx, my, mz) = minus.gauss();
+        Ok(SelfTestReport {
+            delta: ((px - mx) / 2.0, (py - my) / 2.0, (pz - mz) / 2.0),
+            channels: plus.channels(),
+            limits,
+        })
+    }
+
+    async fn self_test_fields<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<(MagneticFieldI32, MagneticFieldI32), Error<CommE>> {
+        self.set_extra(delay).await?;
+        let plus = self.measure_raw().await?;
+        self.reset_extra(delay).await?;
+        let minus = self.measure_raw().await?;
+        Ok((plus.into(), minus.into()))
+    }
+
+    /// Trigger a single measurement and read it
+    async fn measure_raw(&mut self) -> Result<MagneticField, Error<CommE>> {
+        let reg = self.ctrl_reg0 | InternalControl0::TM_M;
+        self.iface.write_register(reg).await?;
+        self.meas_pending = false;
+        self.wait_meas_done().await?;
+        self.release_interrupt(StatusFlags::MEAS_M_DONE).await?;
+        self.read_raw_magnetic_field().await
+    }
+
     /// Get device status
     pub async fn status(&mut self) -> Result<DeviceStatus, Error<CommE>> {
         self.iface
-            .read_register::<Status>()
+            .read_register::<StatusFlags>()
             .await
             .map(DeviceStatus::new)
     }
 
+    /// Wait until a magnetic measurement is done, bounded by the poll budget
+    async fn wait_meas_done(&mut self) -> Result<(), Error<CommE>> {
+        for _ in 0..self.max_polls {
+            if self.status().await?.meas_done() {
+                return Ok(());
+            }
+        }
+        Err(Error::Timeout)
+    }
+
+    /// Clear the interrupts in `flags` by writing 1s to them, which
+    /// de-asserts the INT pin once none is left pending.
+    ///
+    /// Bits that cannot be cleared, like [`StatusFlags::OTP_READ_DONE`],
+    /// are ignored.
+    pub async fn clear_interrupts(&mut self, flags: StatusFlags) -> Result<(), Error<CommE>> {
+        let flags = flags.intersection(StatusFlags::INTERRUPTS);
+        self.iface.write_register(flags).await
+    }
+
+    /// Clear the interrupts in `flags` after a polled wait, so that a later
+    /// wait on the INT pin does not return at once. Nothing is written while
+    /// the interrupt is disabled.
+    async fn release_interrupt(&mut self, flags: StatusFlags) -> Result<(), Error<CommE>> {
+        if self.ctrl_reg0.contains(InternalControl0::INT_MEAS_DONE_EN) {
+            self.clear_interrupts(flags).await?;
+        }
+        Ok(())
+    }
+
+    /// Read the status and clear the interrupts it reports
+    ///
+    /// An interrupt raised between the read and the clear is kept pending.
+    pub async fn acknowledge(&mut self) -> Result<DeviceStatus, Error<CommE>> {
+        let status = self.status().await?;
+        if !status.interrupts().is_empty() {
+            self.clear_interrupts(status.interrupts()).await?;
+        }
+        Ok(status)
+    }
+
+    /// Wait until a temperature measurement is done, bounded by the poll budget
+    async fn wait_temp_done(&mut self) -> Result<(), Error<CommE>> {
+        for _ in 0..self.max_polls {
+            if self.status().await?.temp_done() {
+                return Ok(());
+            }
+        }
+        Err(Error::Timeout)
+    }
+
+    /// Longest time the INT pin may take to signal a measurement: twice the
+    /// measurement time, plus one output period in continuous mode
+    fn interrupt_timeout_us(&self) -> u32 {
+        let mut timeout = 2 * self.ctrl_reg1.bandwidth().measurement_time_us();
+        if self.ctrl_reg2.contains(InternalControl2::CMM_EN) {
+            timeout += self.ctrl_reg2.output_rate().period_us();
+        }
+        timeout
+    }
+
+    /// Wait for the INT pin to signal a finished measurement, reading it
+    /// every [`INT_POLL_INTERVAL_US`] until the interrupt timeout
+    #[maybe_async_cfg::only_if(sync)]
+    fn wait_for_interrupt<P: IntPin, D: DelayNs>(
+        &mut self,
+        int: &mut P,
+        delay: &mut D,
+    ) -> Result<(), Error<CommE>> {
+        for _ in 0..self.interrupt_timeout_us().div_ceil(INT_POLL_INTERVAL_US) {
+            if int.is_high().map_err(|e| Error::Pin(e.kind()))? {
+                return Ok(());
+            }
+            delay.delay_us(INT_POLL_INTERVAL_US);
+        }
+        Err(Error::Timeout)
+    }
+
+    /// Wait for the INT pin to signal a finished measurement, racing it
+    /// against the interrupt timeout
+    #[maybe_async_cfg::only_if(async)]
+    async fn wait_for_interrupt<P: IntPin, D: DelayNs>(
+        &mut self,
+        int: &mut P,
+        delay: &mut D,
+    ) -> Result<(), Error<CommE>> {
+        use core::{future::Future, pin::pin, task::Poll};
+
+        let mut timeout = pin!(delay.delay_us(self.interrupt_timeout_us()));
+        let mut wait = pin!(int.wait_for_high());
+        core::future::poll_fn(|cx| {
+            if let Poll::Ready(result) = wait.as_mut().poll(cx) {
+                return Poll::Ready(result.map_err(|e| Error::Pin(e.kind())));
+            }
+            timeout.as_mut().poll(cx).map(|()| Err(Error::Timeout))
+        })
+        .await
+    }
+
+    /// Wait for the INT pin, de-assert the interrupt and read the measurement.
+    ///
+    /// The interrupt is cleared before reading so that a measurement
+    /// completing during the read raises INT again instead of being lost.
+    pub(crate) async fn read_on_interrupt<P: IntPin, D: DelayNs>(
+        &mut self,
+        int: &mut P,
+        delay: &mut D,
+    ) -> Result<MagneticFieldI32, Error<CommE>> {
+        self.wait_for_interrupt(int, delay).await?;
+        self.clear_interrupts(StatusFlags::MEAS_M_DONE).await?;
+        self.read_magnetic_field().await
+    }
+
     /// Get product ID
     pub async fn product_id(&mut self) -> Result<ProductId, Error<CommE>> {
         self.iface.read_register::<ProductId1>().await
@@ -179,7 +736,8 @@ where
         self.iface.write_register(reg).await?;
 
         // Wait for measurement completion
-        while !self.status().await?.temp_done() {}
+        self.wait_temp_done().await?;
+        self.release_interrupt(StatusFlags::MEAS_T_DONE).await?;
 
         // Read temperature
         let temp = self.iface.read_register::<Temperature>().await?;
@@ -187,10 +745,21 @@ where
         Ok(temp)
     }
 
-    /// Read magnetic field measurement
-    pub async fn read_magnetic_field(&mut self) -> Result<MagneticField, Error<CommE>> {
+    /// Read magnetic field measurement, centred on the null point
+    pub async fn read_magnetic_field(&mut self) -> Result<MagneticFieldI32, Error<CommE>> {
+        self.read_raw_magnetic_field()
+            .await
+            .map(MagneticFieldI32::from)
+    }
+
+    /// Read raw (unsigned) magnetic field measurement
+    pub async fn read_raw_magnetic_field(&mut self) -> Result<MagneticField, Error<CommE>> {
+        let resolution = self.resolution;
+        let channels = self.channels();
         let mut buffer = [0u8; 7]; // For all registers from Xout0 (0x00) to XYZout2 (0x06)
-        self.iface.read_consecutive(0x00, &mut buffer).await?;
+        self.iface
+            .read_consecutive(0x00, &mut buffer[..resolution.output_len()])
+            .await?;
 
         // Extract the 16-bit values
         let x_msb = buffer[0] as u32;
@@ -200,6 +769,17 @@ where
         let z_msb = buffer[4] as u32;
         let z_lsb = buffer[5] as u32;
 
+        if resolution == Resolution::Bits16 {
+            return Ok(MagneticField {
+                x: (x_msb << 8) | x_lsb,
+                y: (y_msb << 8) | y_lsb,
+                z: (z_msb << 8) | z_lsb,
+                resolution,
+                channels,
+            }
+            .with_inhibited_nulled());
+        }
+
         // Extract 2-bit values from XYZout2
         let xyz_2bit = buffer[6];
         let x_2bit = (xyz_2bit >> 6) & 0b11;
@@ -211,105 +791,730 @@ where
         let y = (y_msb << 10) | (y_lsb << 2) | y_2bit as u32;
         let z = (z_msb << 10) | (z_lsb << 2) | z_2bit as u32;
 
-        Ok(MagneticField { x, y, z })
+        Ok(MagneticField {
+            x,
+            y,
+            z,
+            resolution,
+            channels,
+        }
+        .with_inhibited_nulled())
     }
 
     /// Finds offset values from sensor, according page Page 17.
     pub async fn calibrate_offset<D: DelayNs>(
         &mut self,
         delay: &mut D,
-    ) -> Result<MagneticField, Error<CommE>> {
+    ) -> Result<Offset, Error<CommE>> {
+        let (field1, field2) = self.measure_set_reset(delay).await?;
+
+        // Calculate offset
+        let offset = Offset::from_set_reset(field1.raw(), field2.raw());
+
+        self.offset = offset;
+
+        Ok(offset)
+    }
+
+    /// Read all readable registers: `Xout0` to `Status` and the product ID.
+    ///
+    /// The control registers are write-only and are not part of the dump.
+    pub async fn dump_registers(&mut self) -> Result<RegisterDump, Error<CommE>> {
+        let mut data = [0; 9];
+        self.iface.read_consecutive(Xout0::ADDR, &mut data).await?;
+        let product_id = self.product_id().await?;
+
+        let mut output = [0; 7];
+        output.copy_from_slice(&data[..7]);
+        Ok(RegisterDump {
+            output,
+            temperature: data[7],
+            status: data[8],
+            product_id: product_id.raw(),
+        })
+    }
+
+    /// Read the registers from `Xout0` to `Status` for a binary recording.
+    ///
+    /// Take one snapshot after every field or temperature measurement, e.g.
+    /// after [`get_calibrated_field`](Self::get_calibrated_field) and
+    /// [`temperature`](Self::temperature). The replay consumes one snapshot
+    /// per measurement in the same order.
+    pub async fn snapshot(&mut self, timestamp_us: u64) -> Result<Snapshot, Error<CommE>> {
+        let mut registers = [0; SNAPSHOT_REGISTERS];
+        self.iface
+            .read_consecutive(Xout0::ADDR, &mut registers)
+            .await?;
+        Ok(Snapshot::new(timestamp_us, registers))
+    }
+
+    /// Write the driver configuration to the device again, for example after
+    /// a brown-out returned the sensor to its defaults.
+    ///
+    /// The control registers are write-only and the chip offers no other
+    /// readback of its configuration, so neither a reset nor the restored
+    /// configuration can be verified. Call this whenever a brown-out is
+    /// suspected; writing an unchanged configuration is harmless.
+    ///
+    /// Operations in progress such as a triggered measurement are not
+    /// repeated. Continuous mode is re-entered last.
+    pub async fn restore_config(&mut self) -> Result<(), Error<CommE>> {
+        // 3-wire SPI must be enabled before anything is read back
+        self.iface.write_register(self.ctrl_reg3).await?;
+        // Bits the chip clears by itself would start those operations again
+        self.iface
+            .write_register(self.ctrl_reg1.difference(InternalControl1::SELF_CLEARING))
+            .await?;
+        self.iface
+            .write_register(self.ctrl_reg0.difference(InternalControl0::SELF_CLEARING))
+            .await?;
+        self.iface.write_register(self.ctrl_reg2).await
+    }
+
+    /// Take one measurement after a SET and one after a RESET operation.
+    ///
+    /// The sensor is left SET afterwards. The pair can be fed to
+    /// [`Offset::from_set_reset`] or to a
+    /// [`TempCompensator`](crate::compensation::TempCompensator).
+    pub async fn measure_set_reset<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<(MagneticFieldI32, MagneticFieldI32), Error<CommE>> {
         // SET measurement
         self.set(delay).await?;
-        let reg = self.ctrl_reg0 | InternalControl0::TM_M;
-        self.iface.write_register(reg).await?;
-        while !self.status().await?.meas_done() {}
-        let field1 = self.read_magnetic_field().await?;
+        let set = self.measure_raw().await?;
 
         // RESET measurement
         self.reset(delay).await?;
-        let reg = self.ctrl_reg0 | InternalControl0::TM_M;
-        self.iface.write_register(reg).await?;
-        while !self.status().await?.meas_done() {}
-        let field2 = self.read_magnetic_field().await?;
+        let reset = self.measure_raw().await?;
 
-        // Calculate offset
-        let offset = MagneticField {
-            x: ((field1.x_raw() + field2.x_raw()) / 2),
-            y: ((field1.y_raw() + field2.y_raw()) / 2),
-            z: ((field1.z_raw() + field2.z_raw()) / 2),
-        };
+        // Leave the sensor SET so that following measurements are not inverted
+        self.set(delay).await?;
 
-        self.offset = offset;
+        Ok((set.into(), reset.into()))
+    }
 
-        Ok(offset)
+    /// Bridge offset currently removed by `get_calibrated_field`
+    pub fn offset(&self) -> Offset {
+        self.offset
     }
 
-    pub async fn get_calibrated_field(&mut self) -> Result<MagneticField, Error<CommE>> {
-        let reg = self.ctrl_reg0 | InternalControl0::TM_M;
-        self.iface.write_register(reg).await?;
-        while !self.status().await?.meas_done() {}
-        let raw = self.read_magnetic_field().await?;
+    /// Trigger a measurement and remove the offset found by `calibrate_offset`.
+    pub async fn get_calibrated_field(&mut self) -> Result<MagneticFieldI32, Error<CommE>> {
+        let raw = self.measure_raw().await?;
 
-        let offset = self.offset;
-        Ok(MagneticField {
-            x: raw.x_raw() - offset.x,
-            y: raw.y_raw() - offset.y,
-            z: raw.z_raw() - offset.z,
-        })
+        Ok(MagneticFieldI32::from_raw(raw, self.offset))
     }
 }
 
 #[maybe(
-    sync(cfg(not(feature = "async")), keep_self,),
-    async(cfg(feature = "async"), keep_self,)
+    idents(
+        Mmc5983(sync),
+        ReadData(sync),
+        WriteData(sync),
+        DelayNs(sync),
+        IntPin(sync)
+    ),
+    sync(keep_self),
+    async(feature = "async", keep_self)
 )]
 impl<DI, CommE> Mmc5983<DI, mode::Continuous>
 where
     DI: ReadData<Error = Error<CommE>> + WriteData<Error = Error<CommE>>,
 {
     /// Get the measured magnetic field in continuous mode
-    pub async fn magnetic_field(&mut self) -> Result<MagneticField, Error<CommE>> {
-        while !self.status().await?.meas_done() {}
+    pub async fn magnetic_field(&mut self) -> Result<MagneticFieldI32, Error<CommE>> {
+        self.wait_meas_done().await?;
+        self.release_interrupt(StatusFlags::MEAS_M_DONE).await?;
         self.read_magnetic_field().await
     }
+
+    /// Get the next measured magnetic field in continuous mode, waiting on the
+    /// INT pin instead of polling the status register.
+    ///
+    /// The measurement done interrupt must be enabled, which `init` does.
+    /// `delay` bounds the wait to one output period plus twice the
+    /// measurement time, after which [`Error::Timeout`] is returned.
+    pub async fn magnetic_field_on_interrupt<P: IntPin, D: DelayNs>(
+        &mut self,
+        int: &mut P,
+        delay: &mut D,
+    ) -> Result<MagneticFieldI32, Error<CommE>> {
+        self.read_on_interrupt(int, delay).await
+    }
+}
+
+#[maybe(
+    idents(
+        Mmc5983(sync),
+        ReadData(sync),
+        WriteData(sync),
+        DelayNs(sync),
+        IntPin(sync)
+    ),
+    sync(keep_self),
+    async(feature = "async", keep_self)
+)]
+impl<DI> Mmc5983<DI, mode::OneShot> {
+    /// Take a RESET measurement every `every` samples in
+    /// [`tracked_magnetic_field`](Self::tracked_magnetic_field).
+    ///
+    /// The default of 2 alternates SET and RESET measurements. Larger values
+    /// spend fewer SET/RESET pulses but follow offset drift more slowly.
+    /// Values below 2 are treated as 2.
+    pub fn set_offset_tracking(&mut self, every: u32) {
+        self.tracking = OffsetTracking {
+            period: every.max(2),
+            ..OffsetTracking::new()
+        };
+    }
+
+    /// Number of samples per RESET measurement in offset tracking
+    pub fn offset_tracking(&self) -> u32 {
+        self.tracking.period
+    }
 }
 
+#[maybe(
+    idents(
+        Mmc5983(sync),
+        ReadData(sync),
+        WriteData(sync),
+        DelayNs(sync),
+        IntPin(sync)
+    ),
+    sync(keep_self),
+    async(feature = "async", keep_self)
+)]
 impl<DI, CommE> Mmc5983<DI, mode::OneShot>
 where
     DI: ReadData<Error = Error<CommE>> + WriteData<Error = Error<CommE>>,
 {
     /// Get the measured magnetic field in one-shot mode
-    #[cfg(not(feature = "async"))]
-    pub fn magnetic_field(&mut self) -> nb::Result<MagneticField, Error<CommE>> {
+    #[maybe_async_cfg::only_if(sync)]
+    pub fn magnetic_field(&mut self) -> nb::Result<MagneticFieldI32, Error<CommE>> {
         self.magnetic_field_inner()
     }
 
     /// Get the measured magnetic field in one-shot mode
-    #[cfg(feature = "async")]
-    pub async fn magnetic_field(&mut self) -> Result<MagneticField, Error<CommE>> {
-        loop {
+    #[maybe_async_cfg::only_if(async)]
+    pub async fn magnetic_field(&mut self) -> Result<MagneticFieldI32, Error<CommE>> {
+        for _ in 0..self.max_polls {
             match self.magnetic_field_inner().await {
                 Ok(field) => return Ok(field),
                 Err(nb::Error::WouldBlock) => continue,
                 Err(nb::Error::Other(e)) => return Err(e),
             }
         }
+        Err(Error::Timeout)
     }
 
-    #[maybe(
-        sync(cfg(not(feature = "async")), keep_self,),
-        async(cfg(feature = "async"), keep_self,)
-    )]
-    async fn magnetic_field_inner(&mut self) -> nb::Result<MagneticField, Error<CommE>> {
-        let status = self.status().await?;
-        if status.meas_done() {
-            Ok(self.read_magnetic_field().await?)
-        } else {
-            // Trigger new measurement
+    /// Trigger a measurement in one-shot mode and wait on the INT pin instead
+    /// of polling the status register.
+    ///
+    /// The measurement done interrupt must be enabled, which `init` does.
+    /// `delay` bounds the wait to twice the measurement time, after which
+    /// [`Error::Timeout`] is returned.
+    pub async fn magnetic_field_on_interrupt<P: IntPin, D: DelayNs>(
+        &mut self,
+        int: &mut P,
+        delay: &mut D,
+    ) -> Result<MagneticFieldI32, Error<CommE>> {
+        // A measurement finished earlier would release the wait at once
+        self.clear_interrupts(StatusFlags::INTERRUPTS).await?;
+        let reg = self.ctrl_reg0 | InternalControl0::TM_M;
+        self.iface.write_register(reg).await?;
+        self.meas_pending = false;
+        self.read_on_interrupt(int, delay).await
+    }
+
+    /// Measure the magnetic field while tracking the bridge offset.
+    ///
+    /// Most samples are taken after a SET operation. Every
+    /// [`offset_tracking`](Self::offset_tracking) samples one is taken after a
+    /// RESET operation instead, and together with the latest SET sample it
+    /// updates the bridge [`offset`](Self::offset). Every returned sample has
+    /// the current offset removed, and RESET samples are sign-corrected, so
+    /// the caller sees a continuous offset-free series.
+    ///
+    /// The first call takes both a SET and a RESET measurement to seed the
+    /// offset estimate.
+    pub async fn tracked_magnetic_field<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<MagneticFieldI32, Error<CommE>> {
+        let last_set = match self.tracking.last_set {
+            Some(last_set) => last_set,
+            None => {
+                let (set, reset) = self.measure_set_reset(delay).await?;
+                self.offset = Offset::from_set_reset(set.raw(), reset.raw());
+                self.tracking.last_set = Some(set.raw());
+                self.tracking.count = 0;
+                return Ok(MagneticFieldI32::from_raw(set.raw(), self.offset));
+            }
+        };
+
+        self.tracking.count += 1;
+        if self.tracking.count < self.tracking.period {
+            let set = self.measure_raw().await?;
+            self.tracking.last_set = Some(set);
+            return Ok(MagneticFieldI32::from_raw(set, self.offset));
+        }
+
+        self.tracking.count = 0;
+        self.reset(delay).await?;
+        let reset = self.measure_raw().await;
+        // Leave the sensor SET, also when the measurement failed
+        self.set(delay).await?;
+        let reset = reset?;
+        self.offset = Offset::from_set_reset(last_set, reset);
+        Ok(MagneticFieldI32::from_reset(reset, self.offset))
+    }
+
+    async fn magnetic_field_inner(&mut self) -> nb::Result<MagneticFieldI32, Error<CommE>> {
+        if !self.meas_pending {
+            // MEAS_M_DONE stays set until the next trigger, so it only tells
+            // about a measurement that was triggered here
             let reg = self.ctrl_reg0 | InternalControl0::TM_M;
             self.iface.write_register(reg).await?;
-            Err(nb::Error::WouldBlock)
+            self.meas_pending = true;
+            return Err(nb::Error::WouldBlock);
         }
+        if !self.status().await?.meas_done() {
+            return Err(nb::Error::WouldBlock);
+        }
+        self.meas_pending = false;
+        self.release_interrupt(StatusFlags::MEAS_M_DONE).await?;
+        Ok(self.read_magnetic_field().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "async")]
+    use crate::mock::block_on;
+    use crate::{
+        mock::{init_i2c, sim_with_field, Mmc5983Mock, MockDelay, Polarity},
+        ConfigError, MagMode, MagOutputDataRate, SetResetPeriod, StatusEvent,
+    };
+
+    const NULL: u32 = MagneticField::NULL_FIELD;
+
+    #[test]
+    fn init_checks_product_id() {
+        let sim = Mmc5983Mock::new();
+        let mut mag = Mmc5983::new_with_i2c(&sim);
+        assert!(mag.product_id().unwrap().is_correct());
+        mag.init().unwrap();
+        assert!(
+            sim.register(InternalControl0::ADDR) & InternalControl0::INT_MEAS_DONE_EN.bits() != 0
+        );
+        assert!(mag.status().unwrap().otp_read_done());
+    }
+
+    #[test]
+    fn read_raw_field_through_i2c_and_spi() {
+        let sim = sim_with_field((1, -2, 3));
+        let mut mag = init_i2c(&sim);
+        let field = mag.get_calibrated_field().unwrap();
+        assert_eq!(field.raw().x_raw(), NULL + 1);
+        assert_eq!(field.counts(), (1, -2, 3));
+
+        let mut mag = Mmc5983::new_with_spi(&sim);
+        mag.init().unwrap();
+        let field = mag.get_calibrated_field().unwrap();
+        assert_eq!(field.counts(), (1, -2, 3));
+    }
+
+    #[test]
+    fn sixteen_bit_reads() {
+        let sim = sim_with_field((4096 * 4, -8, 4));
+        sim.set_bridge_offset((0, 0, 40));
+        let mut mag = Mmc5983::new_with_spi(&sim);
+        mag.init().unwrap();
+        mag.set_resolution(Resolution::Bits16);
+        mag.calibrate_offset(&mut MockDelay).unwrap();
+        let field = mag.get_calibrated_field().unwrap();
+        assert_eq!(field.resolution(), Resolution::Bits16);
+        assert_eq!(field.raw().x_raw(), 32768 + 4096);
+        assert_eq!(field.counts(), (4096, -2, 1));
+        assert_eq!(field.x_gauss(), 1.0);
+    }
+
+    #[test]
+    fn inhibited_channels_are_absent() {
+        let sim = sim_with_field((100, 200, 300));
+        let mut mag = init_i2c(&sim);
+        mag.get_calibrated_field().unwrap();
+
+        mag.set_channels(Channels::X).unwrap();
+        assert_eq!(mag.channels(), Channels::X);
+        assert_eq!(sim.register(InternalControl1::ADDR), 0b0001_1000);
+        sim.set_field((-100, -200, -300));
+        let field = mag.get_calibrated_field().unwrap();
+        assert_eq!(field.checked_counts(), (Some(-100), None, None));
+        assert_eq!(field.counts(), (-100, 0, 0));
+
+        mag.set_channels(Channels::Yz).unwrap();
+        sim.set_field((1, 2, 3));
+        let field = mag.get_calibrated_field().unwrap();
+        assert_eq!(field.checked_counts(), (None, Some(2), Some(3)));
+        assert_eq!(field.raw().x_raw(), NULL);
+
+        mag.set_channels(Channels::Xyz).unwrap();
+        assert_eq!(sim.register(InternalControl1::ADDR), 0);
+    }
+
+    #[test]
+    fn self_test_measures_coil_field() {
+        let sim = sim_with_field((1000, -2000, 3000));
+        sim.set_bridge_offset((50, 60, 70));
+        sim.set_self_test_field(8192);
+        let mut mag = init_i2c(&sim);
+
+        let report = mag
+            .self_test(&mut MockDelay, SelfTestLimits::new(0.4, 0.6))
+            .unwrap();
+        assert_eq!(report.delta_gauss(), (0.5, 0.5, 0.5));
+        assert!(report.passed());
+        assert_eq!(sim.register(InternalControl3::ADDR), 0);
+        assert_eq!(sim.polarity(), Polarity::Set);
+
+        sim.set_self_test_field(0);
+        let report = mag
+            .self_test(&mut MockDelay, SelfTestLimits::new(0.4, 0.6))
+            .unwrap();
+        assert_eq!(report.axes_passed(), (false, false, false));
+    }
+
+    #[test]
+    fn calibrate_offset_removes_bridge_offset() {
+        let sim = sim_with_field((4000, -3000, 0));
+        sim.set_bridge_offset((-250, 120, 7));
+        let mut mag = Mmc5983::new_with_spi(&sim);
+        mag.init().unwrap();
+
+        let offset = mag.calibrate_offset(&mut MockDelay).unwrap();
+        assert_eq!(
+            offset,
+            Offset {
+                x: -250,
+                y: 120,
+                z: 7
+            }
+        );
+        assert_eq!(sim.polarity(), Polarity::Set);
+
+        let field = mag.get_calibrated_field().unwrap();
+        assert_eq!(field.counts(), (4000, -3000, 0));
+    }
+
+    #[test]
+    fn tracked_field_follows_offset_drift() {
+        let sim = sim_with_field((4000, -3000, 10));
+        sim.set_bridge_offset((-250, 120, 7));
+        let mut mag = init_i2c(&sim);
+        mag.set_offset_tracking(3);
+
+        let field = mag.tracked_magnetic_field(&mut MockDelay).unwrap();
+        assert_eq!(field.counts(), (4000, -3000, 10));
+        assert_eq!(sim.measurements(), 2);
+
+        // Drift shows up on SET samples until the next RESET measurement
+        sim.set_bridge_offset((-200, 100, 7));
+        let field = mag.tracked_magnetic_field(&mut MockDelay).unwrap();
+        assert_eq!(field.counts(), (4050, -3020, 10));
+        mag.tracked_magnetic_field(&mut MockDelay).unwrap();
+
+        let field = mag.tracked_magnetic_field(&mut MockDelay).unwrap();
+        assert_eq!(field.counts(), (4000, -3000, 10));
+        assert_eq!(field.raw().x_raw() as i32, NULL as i32 - 4000 - 200);
+        assert_eq!(mag.offset().x(), -200);
+        assert_eq!(sim.polarity(), Polarity::Set);
+
+        let field = mag.tracked_magnetic_field(&mut MockDelay).unwrap();
+        assert_eq!(field.counts(), (4000, -3000, 10));
+        assert_eq!(sim.measurements(), 6);
+    }
+
+    #[test]
+    fn restore_config_after_brown_out() {
+        let sim = sim_with_field((1, 2, 3));
+        let mut mag = init_i2c(&sim);
+        mag.get_calibrated_field().unwrap();
+        let mut mag = mag
+            .into_continuous(MagOutputDataRate::Hz10, Some(SetResetPeriod::Every25))
+            .unwrap();
+        let ctrl2 = sim.register(InternalControl2::ADDR);
+
+        let dump = mag.dump_registers().unwrap();
+        assert_eq!(dump.product_id(), ProductId1::ID);
+        assert_eq!(dump.output()[0], (NULL >> 10) as u8);
+
+        sim.power_cycle();
+        assert_eq!(sim.register(InternalControl2::ADDR), 0);
+        mag.restore_config().unwrap();
+        assert_eq!(sim.register(InternalControl2::ADDR), ctrl2);
+        assert_eq!(
+            sim.register(InternalControl0::ADDR),
+            InternalControl0::INT_MEAS_DONE_EN.bits()
+        );
+        mag.magnetic_field().unwrap();
+    }
+
+    #[test]
+    fn init_with_config() {
+        let sim = Mmc5983Mock::new();
+        let mut mag = Mmc5983::new_with_i2c(&sim);
+        let config = Config::new()
+            .with_output_rate(MagOutputDataRate::Hz1000)
+            .with_set_period(Some(SetResetPeriod::Every100))
+            .with_interrupt(false)
+            .with_channels(Channels::X)
+            .with_resolution(Resolution::Bits16);
+        assert!(matches!(
+            mag.init_with(config),
+            Err(Error::Config(ConfigError::BandwidthTooLow { .. }))
+        ));
+        assert_eq!(sim.register(InternalControl2::ADDR), 0);
+
+        mag.init_with(config.with_bandwidth(BandwidthMode::Hz800))
+            .unwrap();
+        assert_eq!(sim.register(InternalControl0::ADDR), 0);
+        assert_eq!(sim.register(InternalControl1::ADDR), 0b0001_1011);
+        assert_eq!(sim.register(InternalControl2::ADDR), 0b1011_0111);
+        assert_eq!(mag.resolution(), Resolution::Bits16);
+        assert_eq!(mag.channels(), Channels::X);
+
+        let mut mag = mag.start_continuous().unwrap();
+        assert_eq!(sim.register(InternalControl2::ADDR), 0b1011_1111);
+        assert_eq!(
+            mag.get_mode_config(),
+            MagMode::Continuous {
+                frequency: MagOutputDataRate::Hz1000,
+                set_period: Some(SetResetPeriod::Every100),
+                auto_sr: false
+            }
+        );
+        mag.magnetic_field().unwrap();
+    }
+
+    #[test]
+    fn temperature_measurement() {
+        let sim = Mmc5983Mock::new();
+        sim.set_temperature(100);
+        let mut mag = init_i2c(&sim);
+        assert_eq!(mag.temperature().unwrap().raw(), 100);
+    }
+
+    #[test]
+    fn stalled_device_times_out() {
+        let sim = Mmc5983Mock::new();
+        let mut mag = init_i2c(&sim);
+        sim.set_stalled(true);
+        mag.set_max_polls(50);
+        assert!(matches!(mag.get_calibrated_field(), Err(Error::Timeout)));
+        assert!(matches!(mag.temperature(), Err(Error::Timeout)));
+    }
+
+    #[test]
+    fn interrupt_wait_is_bounded_by_time() {
+        let sim = sim_with_field((5, 6, 7));
+        let mut int = sim.int_pin();
+        let mut mag = init_i2c(&sim);
+        // The poll budget is for status reads and does not limit the INT wait
+        mag.set_max_polls(1);
+        sim.set_latency(50);
+        let field = mag.magnetic_field_on_interrupt(&mut int, &mut MockDelay);
+        assert_eq!(field.unwrap().counts(), (5, 6, 7));
+
+        sim.set_stalled(true);
+        assert!(matches!(
+            mag.magnetic_field_on_interrupt(&mut int, &mut MockDelay),
+            Err(Error::Timeout)
+        ));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_interrupt_wait_times_out() {
+        let sim = sim_with_field((5, 6, 7));
+        let mut int = sim.int_pin();
+        let mut mag = init_i2c(&sim).into_async();
+        sim.set_latency(50);
+        let field = block_on(mag.magnetic_field_on_interrupt(&mut int, &mut MockDelay));
+        assert_eq!(field.unwrap().counts(), (5, 6, 7));
+
+        sim.set_stalled(true);
+        assert!(matches!(
+            block_on(mag.magnetic_field_on_interrupt(&mut int, &mut MockDelay)),
+            Err(Error::Timeout)
+        ));
+    }
+
+    #[test]
+    fn oneshot_nb_measurement() {
+        let sim = sim_with_field((-16384, 0, 0));
+        let mut mag = init_i2c(&sim);
+        let field = nb::block!(mag.magnetic_field()).unwrap();
+        assert_eq!(field.gauss(), (-1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn oneshot_nb_measurement_is_fresh() {
+        let sim = sim_with_field((10, 20, 30));
+        let mut mag = init_i2c(&sim);
+        assert_eq!(
+            nb::block!(mag.magnetic_field()).unwrap().counts(),
+            (10, 20, 30)
+        );
+        sim.set_field((40, 50, 60));
+        assert_eq!(
+            nb::block!(mag.magnetic_field()).unwrap().counts(),
+            (40, 50, 60)
+        );
+    }
+
+    #[test]
+    fn interrupt_driven_measurement() {
+        let sim = sim_with_field((10, 20, 30));
+        let mut int = sim.int_pin();
+        let mut mag = init_i2c(&sim);
+        let field = mag
+            .magnetic_field_on_interrupt(&mut int, &mut MockDelay)
+            .unwrap();
+        assert_eq!(field.counts(), (10, 20, 30));
+        assert!(!sim.interrupt());
+    }
+
+    #[test]
+    fn polled_and_interrupt_reads_mix() {
+        let sim = sim_with_field((10, 20, 30));
+        let mut int = sim.int_pin();
+        let mut mag = init_i2c(&sim);
+        assert_eq!(mag.get_calibrated_field().unwrap().counts(), (10, 20, 30));
+        assert!(!sim.interrupt());
+
+        sim.set_field((99, 99, 99));
+        let field = mag
+            .magnetic_field_on_interrupt(&mut int, &mut MockDelay)
+            .unwrap();
+        assert_eq!(field.counts(), (99, 99, 99));
+
+        mag.temperature().unwrap();
+        assert!(!sim.interrupt());
+        sim.set_field((1, 1, 1));
+        let field = mag
+            .magnetic_field_on_interrupt(&mut int, &mut MockDelay)
+            .unwrap();
+        assert_eq!(field.counts(), (1, 1, 1));
+        assert!(!sim.interrupt());
+    }
+
+    #[test]
+    fn clear_interrupts_by_writing_ones() {
+        let sim = Mmc5983Mock::new();
+        let mut mag = init_i2c(&sim);
+        // Trigger a measurement and poll the status until it is done
+        assert!(matches!(mag.magnetic_field(), Err(nb::Error::WouldBlock)));
+        while !mag.status().unwrap().meas_done() {}
+        assert!(sim.interrupt());
+
+        // Reading the status clears nothing
+        let status = mag.status().unwrap();
+        assert_eq!(status.interrupts(), StatusFlags::MEAS_M_DONE);
+        assert!(sim.interrupt());
+
+        // OTP_READ_DONE cannot be cleared and is ignored
+        mag.clear_interrupts(StatusFlags::OTP_READ_DONE).unwrap();
+        assert!(sim.interrupt());
+        let status = mag.acknowledge().unwrap();
+        assert!(status
+            .events()
+            .eq([StatusEvent::MeasurementDone, StatusEvent::OtpReadDone]));
+        assert!(!sim.interrupt());
+        assert!(mag.status().unwrap().meas_done());
+    }
+
+    #[test]
+    fn status_reports_reserved_bits() {
+        let sim = Mmc5983Mock::new();
+        let mut mag = init_i2c(&sim);
+        assert_eq!(mag.status().unwrap().reserved(), 0);
+
+        sim.set_reserved_status(0x80);
+        let status = mag.status().unwrap();
+        assert_eq!(status.reserved(), 0x80);
+        assert!(status.contains(StatusEvent::OtpReadDone));
+        // Only interrupt bits are written back
+        mag.acknowledge().unwrap();
+        assert_eq!(mag.status().unwrap().reserved(), 0x80);
+    }
+
+    #[test]
+    fn software_reset_clears_control_registers() {
+        let sim = Mmc5983Mock::new();
+        let mut mag = init_i2c(&sim);
+        mag.set_bandwidth(crate::BandwidthMode::Hz800).unwrap();
+        assert_eq!(sim.register(InternalControl1::ADDR), 0b11);
+        mag.init().unwrap();
+        assert_eq!(sim.register(InternalControl1::ADDR), 0b00);
+    }
+
+    /// Delay recording Internal Control 1 whenever it waits
+    struct ResetDelay<'a> {
+        sim: &'a Mmc5983Mock,
+        ctrl1: Option<u8>,
+    }
+
+    impl DelayNs for ResetDelay<'_> {
+        fn delay_ns(&mut self, ns: u32) {
+            if ns >= SW_RESET_TIME_MS * 1_000_000 {
+                self.ctrl1 = Some(self.sim.register(InternalControl1::ADDR));
+            }
+        }
+    }
+
+    #[test]
+    fn init_with_delay_waits_for_software_reset() {
+        let sim = Mmc5983Mock::new();
+        let config = Config::new().with_bandwidth(BandwidthMode::Hz800);
+        let mut mag = Mmc5983::new_with_i2c(&sim);
+        mag.init_with(config).unwrap();
+        assert_eq!(sim.register(InternalControl1::ADDR), 0b11);
+
+        let mut delay = ResetDelay {
+            sim: &sim,
+            ctrl1: None,
+        };
+        mag.init_with_delay(config, &mut delay).unwrap();
+        // Reset, but not yet configured, during the wait
+        assert_eq!(delay.ctrl1, Some(0));
+        assert_eq!(sim.register(InternalControl1::ADDR), 0b11);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn blocking_and_async_in_one_build() {
+        let sim = sim_with_field((16384, 0, 0));
+        let mut mag = init_i2c(&sim);
+        let offset = mag.calibrate_offset(&mut MockDelay).unwrap();
+
+        let mut mag = mag.into_async();
+        assert_eq!(mag.offset(), offset);
+        let field = block_on(mag.get_calibrated_field()).unwrap();
+        assert_eq!(field.counts(), (16384, 0, 0));
+        sim.set_field((0, 16384, 0));
+        let field = block_on(mag.magnetic_field()).unwrap();
+        assert_eq!(field.counts(), (0, 16384, 0));
+        sim.set_field((16384, 0, 0));
+        let mut mag = block_on(mag.into_continuous(MagOutputDataRate::Hz100, None)).unwrap();
+        let field = block_on(mag.magnetic_field()).unwrap();
+        assert_eq!(field.counts(), (16384, 0, 0));
+        let mag = block_on(mag.into_oneshot()).unwrap();
+
+        let mut mag = mag.into_blocking();
+        assert_eq!(mag.get_calibrated_field().unwrap().counts(), (16384, 0, 0));
     }
 }
@@ -0,0 +1,17 @@
+//! Logging macros, no-ops unless the `defmt` feature is enabled
+
+/// Log a `defmt` trace message
+#[cfg(feature = "defmt")]
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        defmt::trace!($($arg)*)
+    };
+}
+
+/// Log a `defmt` trace message
+#[cfg(not(feature = "defmt"))]
+macro_rules! trace {
+    ($fmt:literal $(, $arg:expr)* $(,)?) => {{
+        $(let _ = &$arg;)*
+    }};
+}
@@ -0,0 +1,225 @@
+//! Compass heading
+//!
+//! [`Compass`] turns a calibrated field into a heading in degrees, either
+//! assuming the sensor is level or compensating for tilt with a
+//! caller-supplied gravity vector or roll/pitch angles.
+//!
+//! Headings use the north-east-down body frame: `forward`, `right` and
+//! `down` axes, with the heading increasing clockwise seen from above.
+//! [`AxisMap`] describes how the sensor is mounted relative to that frame.
+//!
+//! ```
+//! use mmc5983_rs::heading::{Axis, AxisMap, Compass};
+//!
+//! // Sensor mounted upside down, X still pointing forward
+//! let compass = Compass::new()
+//!     .with_axis_map(AxisMap::new(Axis::X, Axis::NegY, Axis::NegZ))
+//!     .with_declination(-21.5);
+//! let heading = compass.heading((0.2, 0.0, 0.4));
+//! ```
+
+use core::f32::consts::PI;
+
+use libm::{atan2f, cosf, sinf, sqrtf};
+
+/// A signed sensor axis
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Axis {
+    /// Sensor +X
+    X,
+    /// Sensor -X
+    NegX,
+    /// Sensor +Y
+    Y,
+    /// Sensor -Y
+    NegY,
+    /// Sensor +Z
+    Z,
+    /// Sensor -Z
+    NegZ,
+}
+
+impl Axis {
+    fn pick(&self, (x, y, z): (f32, f32, f32)) -> f32 {
+        match self {
+            Axis::X => x,
+            Axis::NegX => -x,
+            Axis::Y => y,
+            Axis::NegY => -y,
+            Axis::Z => z,
+            Axis::NegZ => -z,
+        }
+    }
+}
+
+/// Sensor axes pointing along the body forward, right and down axes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AxisMap {
+    forward: Axis,
+    right: Axis,
+    down: Axis,
+}
+
+impl Default for AxisMap {
+    fn default() -> Self {
+        Self::new(Axis::X, Axis::Y, Axis::Z)
+    }
+}
+
+impl AxisMap {
+    /// Create a mapping from the sensor axes pointing forward, right and down
+    pub const fn new(forward: Axis, right: Axis, down: Axis) -> Self {
+        Self {
+            forward,
+            right,
+            down,
+        }
+    }
+
+    /// Rotate a sensor-frame vector into the body frame
+    pub fn to_body(&self, v: (f32, f32, f32)) -> (f32, f32, f32) {
+        (self.forward.pick(v), self.right.pick(v), self.down.pick(v))
+    }
+}
+
+/// Heading computation settings
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Compass {
+    axes: AxisMap,
+    declination: f32,
+}
+
+impl Compass {
+    /// Compass with the sensor axes aligned to the body and no declination
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how the sensor is mounted
+    pub fn with_axis_map(self, axes: AxisMap) -> Self {
+        Self { axes, ..self }
+    }
+
+    /// Set the magnetic declination in degrees, positive east
+    pub fn with_declination(self, declination: f32) -> Self {
+        Self {
+            declination,
+            ..self
+        }
+    }
+
+    /// Heading in degrees `[0, 360)`, assuming the body is level.
+    ///
+    /// `field` is a calibrated field in the sensor frame, in any unit.
+    pub fn heading(&self, field: (f32, f32, f32)) -> f32 {
+        let (fx, fy, _) = self.axes.to_body(field);
+        self.finish(atan2f(-fy, fx))
+    }
+
+    /// Tilt-compensated heading in degrees `[0, 360)`, from roll and pitch in
+    /// degrees.
+    pub fn heading_with_attitude(&self, field: (f32, f32, f32), roll: f32, pitch: f32) -> f32 {
+        let (roll, pitch) = (roll.to_radians(), pitch.to_radians());
+        let (bx, by, bz) = self.axes.to_body(field);
+        let (sin_r, cos_r) = (sinf(roll), cosf(roll));
+        let (sin_p, cos_p) = (sinf(pitch), cosf(pitch));
+
+        // De-rotate the field into the horizontal plane
+        let hx = bx * cos_p + by * sin_p * sin_r + bz * sin_p * cos_r;
+        let hy = by * cos_r - bz * sin_r;
+        self.finish(atan2f(-hy, hx))
+    }
+
+    /// Tilt-compensated heading in degrees `[0, 360)`, from a gravity vector.
+    ///
+    /// `gravity` is given in the body frame and points down, i.e. it is
+    /// `(0, 0, 1)` when level. Accelerometers reporting +1 g upwards at rest
+    /// need their reading negated.
+    pub fn heading_with_gravity(&self, field: (f32, f32, f32), gravity: (f32, f32, f32)) -> f32 {
+        let (roll, pitch) = roll_pitch(gravity);
+        self.heading_with_attitude(field, roll, pitch)
+    }
+
+    /// Apply declination and wrap to `[0, 360)` degrees
+    fn finish(&self, heading: f32) -> f32 {
+        let degrees = heading * (180.0 / PI) + self.declination;
+        let wrapped = degrees % 360.0;
+        if wrapped < 0.0 {
+            wrapped + 360.0
+        } else {
+            wrapped
+        }
+    }
+}
+
+/// Roll and pitch in degrees from a body-frame gravity vector pointing down
+pub fn roll_pitch((gx, gy, gz): (f32, f32, f32)) -> (f32, f32) {
+    let roll = atan2f(gy, gz);
+    let pitch = atan2f(-gx, sqrtf(gy * gy + gz * gz));
+    (roll.to_degrees(), pitch.to_degrees())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Horizontal field of 0.2 G with 0.4 G dip, seen by a level body
+    /// pointing at `heading` degrees.
+    fn earth_field(heading: f32) -> (f32, f32, f32) {
+        let h = heading.to_radians();
+        (0.2 * cosf(h), -0.2 * sinf(h), 0.4)
+    }
+
+    /// Body-frame field after rolling and pitching the body
+    fn tilted(field: (f32, f32, f32), roll: f32, pitch: f32) -> (f32, f32, f32) {
+        let (r, p) = (roll.to_radians(), pitch.to_radians());
+        let (x, y, z) = field;
+        // Pitch about the right axis, then roll about the forward axis
+        let (x, z) = (x * cosf(p) - z * sinf(p), x * sinf(p) + z * cosf(p));
+        let (y, z) = (y * cosf(r) + z * sinf(r), -y * sinf(r) + z * cosf(r));
+        (x, y, z)
+    }
+
+    fn assert_angle(actual: f32, expected: f32) {
+        let diff = (actual - expected + 540.0) % 360.0 - 180.0;
+        assert!(diff.abs() < 0.01, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn level_heading() {
+        let compass = Compass::new();
+        for heading in [0.0, 45.0, 90.0, 180.0, 270.0, 359.0] {
+            assert_angle(compass.heading(earth_field(heading)), heading);
+        }
+    }
+
+    #[test]
+    fn declination_wraps() {
+        let compass = Compass::new().with_declination(-20.0);
+        assert_angle(compass.heading(earth_field(10.0)), 350.0);
+        let compass = Compass::new().with_declination(20.0);
+        assert_angle(compass.heading(earth_field(350.0)), 10.0);
+    }
+
+    #[test]
+    fn axis_mapping() {
+        // Sensor rotated 90° clockwise: sensor +Y points forward
+        let compass = Compass::new().with_axis_map(AxisMap::new(Axis::Y, Axis::NegX, Axis::Z));
+        let (f, r, d) = earth_field(30.0);
+        let sensor = (-r, f, d);
+        assert_angle(compass.heading(sensor), 30.0);
+    }
+
+    #[test]
+    fn tilt_compensation() {
+        let compass = Compass::new();
+        let (roll, pitch) = (15.0, -25.0);
+        let field = tilted(earth_field(120.0), roll, pitch);
+        assert_angle(compass.heading_with_attitude(field, roll, pitch), 120.0);
+
+        let gravity = tilted((0.0, 0.0, 1.0), roll, pitch);
+        let (r, p) = roll_pitch(gravity);
+        assert!((r - roll).abs() < 0.01 && (p - pitch).abs() < 0.01);
+        assert_angle(compass.heading_with_gravity(field, gravity), 120.0);
+    }
+}
@@ -1,14 +1,18 @@
 //! I2C/SPI interfaces
 use maybe_async_cfg::maybe;
 
-#[cfg(not(feature = "async"))]
-use embedded_hal::{i2c, spi};
+use embedded_hal::{
+    i2c::I2c,
+    spi::{Operation, SpiDevice},
+};
 #[cfg(feature = "async")]
-use embedded_hal_async::{i2c, spi};
+use embedded_hal_async::{i2c::I2c as I2cAsync, spi::SpiDevice as SpiDeviceAsync};
 
 use crate::{
     private,
-    register_address::{RegRead, RegWrite},
+    record::{Recording, RecordingError, Snapshot, SNAPSHOT_REGISTERS},
+    register_address::{ProductId1, RegRead, RegWrite, Tout, Xout0},
+    types::StatusFlags,
     Error,
 };
 
@@ -27,11 +31,71 @@ pub struct SpiInterface<SPI> {
     pub(crate) spi: SPI,
 }
 
+/// 3-wire SPI interface
+///
+/// For boards sharing a single SDIO line for data in and out. Reads are
+/// performed half-duplex: the command byte is written, then the data is read
+/// back over the same line.
+#[derive(Debug)]
+pub struct Spi3WireInterface<SPI> {
+    pub(crate) spi: SPI,
+}
+
+/// Replay interface
+///
+/// Feeds a [`Recording`] back through the driver instead of a bus. Register
+/// writes are accepted and ignored. Reads return the current snapshot, and
+/// every read starting at `Xout0` or `Tout` moves on to the next one, so
+/// each field or temperature measurement consumes one snapshot, in the order
+/// they were recorded. Measurements and the OTP read always
+/// report as done and the product ID reads as expected, so `init` succeeds.
+#[derive(Debug)]
+pub struct ReplayInterface<'a> {
+    pub(crate) recording: Recording<'a>,
+    pub(crate) current: Option<Snapshot>,
+}
+
+impl<'a> ReplayInterface<'a> {
+    pub(crate) fn new(mut recording: Recording<'a>) -> Self {
+        let current = recording.pop();
+        Self { recording, current }
+    }
+
+    /// Snapshots not replayed yet, including the current one
+    pub fn remaining(&self) -> usize {
+        self.recording.len() + usize::from(self.current.is_some())
+    }
+
+    fn read(&mut self, start_addr: u8, buffer: &mut [u8]) -> Result<(), Error<RecordingError>> {
+        for (addr, value) in (start_addr..).zip(buffer.iter_mut()) {
+            let snapshot = || {
+                self.current
+                    .map(|snapshot| snapshot.registers()[usize::from(addr)])
+                    .ok_or(Error::Comm(RecordingError::EndOfRecording))
+            };
+            *value = match addr {
+                ProductId1::ADDR => ProductId1::ID,
+                StatusFlags::ADDR => {
+                    let done = StatusFlags::MEAS_M_DONE
+                        | StatusFlags::MEAS_T_DONE
+                        | StatusFlags::OTP_READ_DONE;
+                    snapshot()? | done.bits()
+                }
+                _ if usize::from(addr) < SNAPSHOT_REGISTERS => snapshot()?,
+                // Write-only control registers
+                _ => 0,
+            };
+        }
+        trace!("replay read {=u8:#04x}: {=[u8]:#04x}", start_addr, buffer);
+        if start_addr == Xout0::ADDR || start_addr == Tout::ADDR {
+            self.current = self.recording.pop();
+        }
+        Ok(())
+    }
+}
+
 /// Write data
-#[maybe(
-    sync(cfg(not(feature = "async")), keep_self,),
-    async(cfg(feature = "async"), keep_self,)
-)]
+#[maybe(sync(keep_self), async(feature = "async"))]
 pub trait WriteData: private::Sealed {
     /// Error type
     type Error;
@@ -41,16 +105,18 @@ pub trait WriteData: private::Sealed {
 }
 
 #[maybe(
-    sync(cfg(not(feature = "async")), keep_self,),
-    async(cfg(feature = "async"), keep_self,)
+    idents(ReadData(sync), WriteData(sync), I2c(sync), SpiDevice(sync)),
+    sync(keep_self),
+    async(feature = "async", keep_self)
 )]
 impl<I2C, E> WriteData for I2cInterface<I2C>
 where
-    I2C: i2c::I2c<Error = E>,
+    I2C: I2c<Error = E>,
 {
     type Error = Error<E>;
 
     async fn write_register<R: RegWrite>(&mut self, reg: R) -> Result<(), Self::Error> {
+        trace!("write {=u8:#04x}: {=u8:#010b}", R::ADDR, reg.data());
         let payload: [u8; 2] = [R::ADDR, reg.data()];
         self.i2c
             .write(MMC5983_ADDR, &payload)
@@ -60,26 +126,57 @@ where
 }
 
 #[maybe(
-    sync(cfg(not(feature = "async")), keep_self,),
-    async(cfg(feature = "async"), keep_self,)
+    idents(ReadData(sync), WriteData(sync), I2c(sync), SpiDevice(sync)),
+    sync(keep_self),
+    async(feature = "async", keep_self)
 )]
 impl<SPI, CommE> WriteData for SpiInterface<SPI>
 where
-    SPI: spi::SpiDevice<u8, Error = CommE>,
+    SPI: SpiDevice<u8, Error = CommE>,
 {
     type Error = Error<CommE>;
 
     async fn write_register<R: RegWrite>(&mut self, reg: R) -> Result<(), Self::Error> {
+        trace!("write {=u8:#04x}: {=u8:#010b}", R::ADDR, reg.data());
         let payload: [u8; 2] = [R::ADDR & !SPI_RW, reg.data()];
         self.spi.write(&payload).await.map_err(Error::Comm)
     }
 }
 
-/// Read data
 #[maybe(
-    sync(cfg(not(feature = "async")), keep_self,),
-    async(cfg(feature = "async"), keep_self,)
+    idents(ReadData(sync), WriteData(sync), I2c(sync), SpiDevice(sync)),
+    sync(keep_self),
+    async(feature = "async", keep_self)
 )]
+impl<SPI, CommE> WriteData for Spi3WireInterface<SPI>
+where
+    SPI: SpiDevice<u8, Error = CommE>,
+{
+    type Error = Error<CommE>;
+
+    async fn write_register<R: RegWrite>(&mut self, reg: R) -> Result<(), Self::Error> {
+        trace!("write {=u8:#04x}: {=u8:#010b}", R::ADDR, reg.data());
+        let payload: [u8; 2] = [R::ADDR & !SPI_RW, reg.data()];
+        self.spi.write(&payload).await.map_err(Error::Comm)
+    }
+}
+
+#[maybe(
+    idents(ReadData(sync), WriteData(sync), I2c(sync), SpiDevice(sync)),
+    sync(keep_self),
+    async(feature = "async", keep_self)
+)]
+impl WriteData for ReplayInterface<'_> {
+    type Error = Error<RecordingError>;
+
+    async fn write_register<R: RegWrite>(&mut self, reg: R) -> Result<(), Self::Error> {
+        trace!("replay write {=u8:#04x}: {=u8:#010b}", R::ADDR, reg.data());
+        Ok(())
+    }
+}
+
+/// Read data
+#[maybe(sync(keep_self), async(feature = "async"))]
 pub trait ReadData: private::Sealed {
     /// Error type
     type Error;
@@ -98,12 +195,13 @@ pub trait ReadData: private::Sealed {
 const SPI_RW: u8 = 1 << 7;
 
 #[maybe(
-    sync(cfg(not(feature = "async")), keep_self,),
-    async(cfg(feature = "async"), keep_self,)
+    idents(ReadData(sync), WriteData(sync), I2c(sync), SpiDevice(sync)),
+    sync(keep_self),
+    async(feature = "async", keep_self)
 )]
 impl<I2C, E> ReadData for I2cInterface<I2C>
 where
-    I2C: i2c::I2c<Error = E>,
+    I2C: I2c<Error = E>,
 {
     type Error = Error<E>;
 
@@ -113,6 +211,7 @@ where
             .write_read(MMC5983_ADDR, &[R::ADDR], &mut data)
             .await
             .map_err(Error::Comm)?;
+        trace!("read {=u8:#04x}: {=u8:#010b}", R::ADDR, data[0]);
 
         Ok(R::from_data(data[0]))
     }
@@ -125,17 +224,20 @@ where
         self.i2c
             .write_read(MMC5983_ADDR, &[start_addr], buffer)
             .await
-            .map_err(Error::Comm)
+            .map_err(Error::Comm)?;
+        trace!("read {=u8:#04x}: {=[u8]:#04x}", start_addr, buffer);
+        Ok(())
     }
 }
 
 #[maybe(
-    sync(cfg(not(feature = "async")), keep_self,),
-    async(cfg(feature = "async"), keep_self,)
+    idents(ReadData(sync), WriteData(sync), I2c(sync), SpiDevice(sync)),
+    sync(keep_self),
+    async(feature = "async", keep_self)
 )]
 impl<SPI, CommE> ReadData for SpiInterface<SPI>
 where
-    SPI: spi::SpiDevice<u8, Error = CommE>,
+    SPI: SpiDevice<u8, Error = CommE>,
 {
     type Error = Error<CommE>;
 
@@ -145,6 +247,7 @@ where
             .transfer_in_place(&mut data)
             .await
             .map_err(Error::Comm)?;
+        trace!("read {=u8:#04x}: {=u8:#010b}", R::ADDR, data[1]);
 
         Ok(R::from_data(data[1]))
     }
@@ -172,6 +275,348 @@ where
             .map_err(Error::Comm)?;
 
         buffer.copy_from_slice(&data_slice[1..]);
+        trace!("read {=u8:#04x}: {=[u8]:#04x}", start_addr, buffer);
+        Ok(())
+    }
+}
+
+#[maybe(
+    idents(ReadData(sync), WriteData(sync), I2c(sync), SpiDevice(sync)),
+    sync(keep_self),
+    async(feature = "async", keep_self)
+)]
+impl<SPI, CommE> ReadData for Spi3WireInterface<SPI>
+where
+    SPI: SpiDevice<u8, Error = CommE>,
+{
+    type Error = Error<CommE>;
+
+    async fn read_register<R: RegRead>(&mut self) -> Result<R::Output, Self::Error> {
+        let mut data = [0];
+        self.read_consecutive(R::ADDR, &mut data).await?;
+        Ok(R::from_data(data[0]))
+    }
+
+    async fn read_consecutive(
+        &mut self,
+        start_addr: u8,
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.spi
+            .transaction(&mut [
+                Operation::Write(&[SPI_RW | start_addr]),
+                Operation::Read(buffer),
+            ])
+            .await
+            .map_err(Error::Comm)?;
+        trace!("read {=u8:#04x}: {=[u8]:#04x}", start_addr, buffer);
         Ok(())
     }
 }
+
+#[maybe(
+    idents(ReadData(sync), WriteData(sync), I2c(sync), SpiDevice(sync)),
+    sync(keep_self),
+    async(feature = "async", keep_self)
+)]
+impl ReadData for ReplayInterface<'_> {
+    type Error = Error<RecordingError>;
+
+    async fn read_register<R: RegRead>(&mut self) -> Result<R::Output, Self::Error> {
+        let mut data = [0];
+        self.read(R::ADDR, &mut data)?;
+        Ok(R::from_data(data[0]))
+    }
+
+    async fn read_consecutive(
+        &mut self,
+        start_addr: u8,
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.read(start_addr, buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{cell::RefCell, convert::Infallible};
+
+    use embedded_hal::{digital, i2c, spi};
+
+    use super::*;
+    use crate::{
+        mock::{init_i2c, sim_with_field, Mmc5983Mock, MockDelay},
+        register_address::{InternalControl1, InternalControl3},
+        BandwidthMode, Mmc5983, SelfTestLimits,
+    };
+
+    /// SPI bus routing each transfer to the mock whose chip select is low
+    struct SharedSpiBus<'a> {
+        sims: [&'a Mmc5983Mock; 2],
+        selected: &'a core::cell::Cell<Option<usize>>,
+    }
+
+    impl SharedSpiBus<'_> {
+        fn forward(&mut self, op: spi::Operation<'_, u8>) -> Result<(), spi::ErrorKind> {
+            let index = self.selected.get().ok_or(spi::ErrorKind::ChipSelectFault)?;
+            SpiDevice::transaction(&mut self.sims[index], &mut [op])
+        }
+    }
+
+    impl spi::ErrorType for SharedSpiBus<'_> {
+        type Error = spi::ErrorKind;
+    }
+
+    impl spi::SpiBus for SharedSpiBus<'_> {
+        fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+            self.forward(spi::Operation::Read(words))
+        }
+
+        fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+            self.forward(spi::Operation::Write(words))
+        }
+
+        fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+            self.forward(spi::Operation::Transfer(read, write))
+        }
+
+        fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+            self.forward(spi::Operation::TransferInPlace(words))
+        }
+
+        fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    struct ChipSelect<'a> {
+        index: usize,
+        selected: &'a core::cell::Cell<Option<usize>>,
+    }
+
+    impl digital::ErrorType for ChipSelect<'_> {
+        type Error = Infallible;
+    }
+
+    impl digital::OutputPin for ChipSelect<'_> {
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            self.selected.set(Some(self.index));
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            if self.selected.get() == Some(self.index) {
+                self.selected.set(None);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn three_wire_spi() {
+        let sim = sim_with_field((1, -2, 3));
+        let mut mag = Mmc5983::new_with_spi_3wire(&sim);
+        mag.init().unwrap();
+        assert_eq!(
+            sim.register(InternalControl3::ADDR),
+            InternalControl3::SPI_3W.bits()
+        );
+        let field = mag.get_calibrated_field().unwrap();
+        assert_eq!(field.counts(), (1, -2, 3));
+
+        // Self-test keeps the chip in 3-wire mode
+        mag.self_test(&mut MockDelay, SelfTestLimits::new(0.0, 1.0))
+            .unwrap();
+        assert_eq!(
+            sim.register(InternalControl3::ADDR),
+            InternalControl3::SPI_3W.bits()
+        );
+
+        // Full-duplex reads no longer see the chip
+        let mut mag = Mmc5983::new_with_spi(&sim);
+        assert!(!mag.product_id().unwrap().is_correct());
+    }
+
+    #[test]
+    fn shared_i2c_bus_refcell() {
+        let sim = sim_with_field((1, -2, 3));
+        let bus = RefCell::new(&sim);
+        let mut mag = Mmc5983::new_with_i2c(embedded_hal_bus::i2c::RefCellDevice::new(&bus));
+        let mut other = embedded_hal_bus::i2c::RefCellDevice::new(&bus);
+
+        mag.init().unwrap();
+        // Another device on the bus, here at an address nobody answers
+        assert!(i2c::I2c::write(&mut other, 0x19, &[0x0F]).is_err());
+        assert_eq!(mag.get_calibrated_field().unwrap().counts(), (1, -2, 3));
+    }
+
+    #[test]
+    fn shared_i2c_bus_critical_section() {
+        let sim = sim_with_field((1, -2, 3));
+        let bus = critical_section::Mutex::new(RefCell::new(&sim));
+        let mut mag =
+            Mmc5983::new_with_i2c(embedded_hal_bus::i2c::CriticalSectionDevice::new(&bus));
+        mag.init().unwrap();
+        assert_eq!(mag.get_calibrated_field().unwrap().counts(), (1, -2, 3));
+    }
+
+    #[test]
+    fn shared_i2c_bus_mutex() {
+        extern crate std;
+
+        let sim = sim_with_field((1, -2, 3));
+        let bus = std::sync::Mutex::new(&sim);
+        let mut mag = Mmc5983::new_with_i2c(embedded_hal_bus::i2c::MutexDevice::new(&bus));
+        mag.init().unwrap();
+        assert_eq!(mag.get_calibrated_field().unwrap().counts(), (1, -2, 3));
+        assert!(!bus.is_poisoned());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn shared_i2c_bus_async_mutex() {
+        use embassy_embedded_hal::shared_bus::asynch::i2c::I2cDevice;
+        use embassy_sync::{blocking_mutex::raw::NoopRawMutex, mutex::Mutex};
+
+        use crate::{mock::block_on, Mmc5983Async};
+
+        extern crate std;
+
+        // The embassy device needs a 'static bus, as with a `StaticCell` on
+        // the target
+        let sim = std::boxed::Box::leak(std::boxed::Box::new(sim_with_field((1, -2, 3))));
+        let bus = Mutex::<NoopRawMutex, _>::new(&*sim);
+        let mut mag = Mmc5983Async::new_with_i2c(I2cDevice::new(&bus));
+        let mut other = Mmc5983Async::new_with_i2c(I2cDevice::new(&bus));
+
+        block_on(mag.init()).unwrap();
+        let (field, id) = block_on(embassy_futures::join::join(
+            mag.get_calibrated_field(),
+            other.product_id(),
+        ));
+        assert_eq!(field.unwrap().counts(), (1, -2, 3));
+        assert!(id.unwrap().is_correct());
+        assert!(bus.try_lock().is_ok());
+
+        // One-shot reads as in the micro:bit example see every new field
+        for field in [(4, 5, 6), (-7, 8, -9)] {
+            sim.set_field(field);
+            assert_eq!(block_on(mag.magnetic_field()).unwrap().counts(), field);
+        }
+    }
+
+    #[test]
+    fn replay_mixed_temperature_and_field() {
+        use crate::record::{Recording, RECORDING_HEADER, SNAPSHOT_LEN};
+
+        let sim = sim_with_field((100, 0, 0));
+        let mut mag = init_i2c(&sim);
+
+        let mut data = [0; RECORDING_HEADER.len() + 4 * SNAPSHOT_LEN];
+        data[..RECORDING_HEADER.len()].copy_from_slice(&RECORDING_HEADER);
+        let mut chunks = data[RECORDING_HEADER.len()..].chunks_exact_mut(SNAPSHOT_LEN);
+        let mut capture = |mag: &mut Mmc5983<_, _>| {
+            let snapshot = mag.snapshot(0).unwrap();
+            chunks.next().unwrap().copy_from_slice(&snapshot.to_bytes());
+        };
+        mag.get_calibrated_field().unwrap();
+        capture(&mut mag);
+        sim.set_temperature(80);
+        mag.temperature().unwrap();
+        capture(&mut mag);
+        sim.set_field((200, 0, 0));
+        mag.get_calibrated_field().unwrap();
+        capture(&mut mag);
+        sim.set_temperature(90);
+        mag.temperature().unwrap();
+        capture(&mut mag);
+
+        let mut replay = Mmc5983::new_with_replay(Recording::new(&data).unwrap());
+        replay.init().unwrap();
+        assert_eq!(replay.get_calibrated_field().unwrap().counts(), (100, 0, 0));
+        assert_eq!(replay.temperature().unwrap().raw(), 80);
+        assert_eq!(replay.get_calibrated_field().unwrap().counts(), (200, 0, 0));
+        assert_eq!(replay.temperature().unwrap().raw(), 90);
+        assert_eq!(replay.remaining(), 0);
+    }
+
+    #[test]
+    fn multiple_sensors_on_spi_chip_selects() {
+        use embedded_hal_bus::spi::RefCellDevice;
+
+        let (a, b) = (Mmc5983Mock::new(), Mmc5983Mock::new());
+        a.set_field((100, 0, 0));
+        b.set_field((0, 0, -100));
+        let selected = core::cell::Cell::new(None);
+        let bus = RefCell::new(SharedSpiBus {
+            sims: [&a, &b],
+            selected: &selected,
+        });
+        let cs = |index| ChipSelect {
+            index,
+            selected: &selected,
+        };
+
+        let mut mag_a = Mmc5983::new_with_spi(RefCellDevice::new_no_delay(&bus, cs(0)).unwrap());
+        let mut mag_b = Mmc5983::new_with_spi(RefCellDevice::new_no_delay(&bus, cs(1)).unwrap());
+        mag_a.init().unwrap();
+        mag_b.init().unwrap();
+        mag_b.set_bandwidth(BandwidthMode::Hz800).unwrap();
+
+        assert_eq!(mag_a.get_calibrated_field().unwrap().counts(), (100, 0, 0));
+        assert_eq!(mag_b.get_calibrated_field().unwrap().counts(), (0, 0, -100));
+        assert_eq!(a.register(InternalControl1::ADDR), 0);
+        assert_eq!(b.register(InternalControl1::ADDR), 0b11);
+    }
+
+    #[test]
+    fn replay_recorded_session() {
+        use crate::heading::Compass;
+        use crate::record::{Recording, RecordingError, RECORDING_HEADER, SNAPSHOT_LEN};
+
+        let sim = sim_with_field((4000, -3000, 500));
+        sim.set_bridge_offset((-250, 120, 7));
+        let mut mag = init_i2c(&sim);
+
+        // SET and RESET measurements, then three samples
+        let mut data = [0; RECORDING_HEADER.len() + 5 * SNAPSHOT_LEN];
+        data[..RECORDING_HEADER.len()].copy_from_slice(&RECORDING_HEADER);
+        let mut chunks = data[RECORDING_HEADER.len()..].chunks_exact_mut(SNAPSHOT_LEN);
+        let mut capture = |mag: &mut Mmc5983<_, _>, t| {
+            let snapshot = mag.snapshot(t).unwrap();
+            chunks.next().unwrap().copy_from_slice(&snapshot.to_bytes());
+        };
+        mag.set(&mut MockDelay).unwrap();
+        mag.get_calibrated_field().unwrap();
+        capture(&mut mag, 0);
+        mag.reset(&mut MockDelay).unwrap();
+        mag.get_calibrated_field().unwrap();
+        capture(&mut mag, 1_000);
+        mag.set(&mut MockDelay).unwrap();
+        let offset = mag.calibrate_offset(&mut MockDelay).unwrap();
+        let mut expected = [(0, 0, 0); 3];
+        for (i, counts) in expected.iter_mut().enumerate() {
+            *counts = mag.get_calibrated_field().unwrap().counts();
+            capture(&mut mag, 2_000 + i as u64 * 10_000);
+        }
+
+        let recording = Recording::new(&data).unwrap();
+        assert_eq!(recording.len(), 5);
+        let mut replay = Mmc5983::new_with_replay(recording);
+        replay.init().unwrap();
+        assert_eq!(replay.calibrate_offset(&mut MockDelay).unwrap(), offset);
+        assert_eq!(replay.remaining(), 3);
+
+        let compass = Compass::new();
+        for counts in expected {
+            let field = replay.get_calibrated_field().unwrap();
+            assert_eq!(field.counts(), counts);
+            let heading = compass.heading(field.gauss());
+            assert!((heading - compass.heading((4000.0, -3000.0, 500.0))).abs() < 1e-3);
+        }
+        assert!(matches!(
+            replay.get_calibrated_field(),
+            Err(Error::Comm(RecordingError::EndOfRecording))
+        ));
+    }
+}
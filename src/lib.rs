@@ -7,24 +7,50 @@
 //! This is a platform agnostic Rust driver for the MMC5983MA magnetometer
 //! using the embedded-hal traits.
 
+#[macro_use]
+mod fmt;
+
+pub mod calibration;
+pub mod compensation;
+mod config;
 mod device_impl;
+pub mod heading;
 pub mod interface;
 mod magnetometer;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+pub mod record;
 pub mod register_address;
+#[cfg(feature = "async")]
+pub mod stream;
 mod types;
 
 use core::marker::PhantomData;
 
+use maybe_async_cfg::maybe;
+
+pub use crate::config::{Config, ConfigError};
+
 pub use crate::types::{
-    mode, BandwidthMode, Error, MagMode, MagOutputDataRate, MagneticField, ProductId,
-    SetResetPeriod, Status, Temperature,
+    mode, BandwidthMode, Channels, ClearMode, Error, MagMode, MagOutputDataRate, MagneticField,
+    MagneticFieldI32, Offset, ProductId, RegisterDump, Resolution, SelfTestLimits, SelfTestReport,
+    SetResetPeriod, Status, StatusEvent, StatusFlags, Temperature,
 };
 
+use crate::types::OffsetTracking;
+
 use crate::register_address::{
     InternalControl0, InternalControl1, InternalControl2, InternalControl3,
 };
 
+/// Default number of status reads before giving up on a measurement
+pub const DEFAULT_MAX_POLLS: u32 = 10_000;
+
 /// MMC5983MA device driver
+///
+/// `Mmc5983` is built on the blocking `embedded-hal` traits. With the `async`
+/// feature, `Mmc5983Async` provides the same API on `embedded-hal-async`.
+#[maybe(sync(keep_self), async(feature = "async"))]
 #[derive(Debug)]
 pub struct Mmc5983<DI, MODE> {
     /// Digital interface: I2C or SPI
@@ -35,7 +61,15 @@ pub struct Mmc5983<DI, MODE> {
     ctrl_reg2: InternalControl2,
     ctrl_reg3: InternalControl3,
     /// Driver internal data
-    offset: MagneticField,
+    offset: Offset,
+    /// Maximum number of status reads while waiting for a measurement
+    max_polls: u32,
+    /// Magnetic field output resolution
+    resolution: Resolution,
+    /// One-shot SET/RESET offset tracking state
+    tracking: OffsetTracking,
+    /// A one-shot measurement was triggered and has not been read yet
+    meas_pending: bool,
     /// Operating mode marker
     _mode: PhantomData<MODE>,
 }
@@ -44,5 +78,7 @@ mod private {
     use crate::interface;
     pub trait Sealed {}
     impl<SPI> Sealed for interface::SpiInterface<SPI> {}
+    impl<SPI> Sealed for interface::Spi3WireInterface<SPI> {}
     impl<I2C> Sealed for interface::I2cInterface<I2C> {}
+    impl Sealed for interface::ReplayInterface<'_> {}
 }
@@ -1,16 +1,23 @@
 use maybe_async_cfg::maybe;
 
 use crate::{
+    config::check_rate,
     interface::{ReadData, WriteData},
     mode,
     register_address::InternalControl2,
     types::SetResetPeriod,
     Error, MagMode, MagOutputDataRate, Mmc5983,
 };
+#[cfg(feature = "async")]
+use crate::{
+    interface::{ReadDataAsync, WriteDataAsync},
+    Mmc5983Async,
+};
 
 #[maybe(
-    sync(cfg(not(feature = "async")), keep_self,),
-    async(cfg(feature = "async"), keep_self,)
+    idents(Mmc5983(sync), ReadData(sync), WriteData(sync)),
+    sync(keep_self),
+    async(feature = "async", keep_self)
 )]
 impl<DI, CommE> Mmc5983<DI, mode::OneShot>
 where
@@ -21,11 +28,18 @@ where
     /// # Arguments
     /// * `frequency` - The measurement frequency in continuous mode
     /// * `set_period` - Optional period for automatic SET/RESET operations
+    ///
+    /// Fails with
+    /// [`ConfigError::BandwidthTooLow`](crate::ConfigError::BandwidthTooLow)
+    /// if the configured bandwidth cannot keep up with `frequency`, see
+    /// [`BandwidthMode::for_output_rate`](crate::BandwidthMode::for_output_rate).
     pub async fn into_continuous(
         mut self,
         frequency: MagOutputDataRate,
         set_period: Option<SetResetPeriod>,
     ) -> Result<Mmc5983<DI, mode::Continuous>, Error<CommE>> {
+        check_rate(frequency, self.ctrl_reg1.bandwidth()).map_err(Error::Config)?;
+
         // Enable automatic SET/RESET if a period is specified
         if let Some(period) = set_period {
             let reg = self.ctrl_reg2.with_set_period(period) | InternalControl2::EN_PRD_SET;
@@ -45,14 +59,59 @@ where
             ctrl_reg2: self.ctrl_reg2,
             ctrl_reg3: self.ctrl_reg3,
             offset: self.offset,
+            max_polls: self.max_polls,
+            resolution: self.resolution,
+            tracking: self.tracking,
+            meas_pending: false,
+            _mode: core::marker::PhantomData,
+        })
+    }
+
+    /// Change the magnetometer to continuous measurement mode with the
+    /// output data rate and SET period given to
+    /// [`init_with`](Mmc5983::init_with)
+    pub async fn start_continuous(mut self) -> Result<Mmc5983<DI, mode::Continuous>, Error<CommE>> {
+        check_rate(self.ctrl_reg2.output_rate(), self.ctrl_reg1.bandwidth())
+            .map_err(Error::Config)?;
+
+        let reg = self.ctrl_reg2 | InternalControl2::CMM_EN;
+        self.iface.write_register(reg).await?;
+        self.ctrl_reg2 = reg;
+
+        Ok(Mmc5983 {
+            iface: self.iface,
+            ctrl_reg0: self.ctrl_reg0,
+            ctrl_reg1: self.ctrl_reg1,
+            ctrl_reg2: self.ctrl_reg2,
+            ctrl_reg3: self.ctrl_reg3,
+            offset: self.offset,
+            max_polls: self.max_polls,
+            resolution: self.resolution,
+            tracking: self.tracking,
+            meas_pending: false,
             _mode: core::marker::PhantomData,
         })
     }
 }
 
 #[maybe(
-    sync(cfg(not(feature = "async")), keep_self,),
-    async(cfg(feature = "async"), keep_self,)
+    idents(Mmc5983(sync), ReadData(sync), WriteData(sync)),
+    sync(keep_self),
+    async(feature = "async", keep_self)
+)]
+impl<DI> Mmc5983<DI, mode::OneShot> {
+    /// Get current measurement mode configuration
+    pub fn get_mode_config(&self) -> MagMode {
+        MagMode::OneShot {
+            auto_sr: self.auto_sr(),
+        }
+    }
+}
+
+#[maybe(
+    idents(Mmc5983(sync), ReadData(sync), WriteData(sync)),
+    sync(keep_self),
+    async(feature = "async", keep_self)
 )]
 impl<DI, CommE> Mmc5983<DI, mode::Continuous>
 where
@@ -74,15 +133,24 @@ where
             ctrl_reg2: self.ctrl_reg2,
             ctrl_reg3: self.ctrl_reg3,
             offset: self.offset,
+            max_polls: self.max_polls,
+            resolution: self.resolution,
+            tracking: self.tracking,
+            meas_pending: false,
             _mode: core::marker::PhantomData,
         })
     }
 
-    /// Change the continuous mode measurement frequency
+    /// Change the continuous mode measurement frequency.
+    ///
+    /// Fails with
+    /// [`ConfigError::BandwidthTooLow`](crate::ConfigError::BandwidthTooLow)
+    /// if the configured bandwidth cannot keep up with `frequency`.
     pub async fn set_frequency(
         &mut self,
         frequency: MagOutputDataRate,
     ) -> Result<(), Error<CommE>> {
+        check_rate(frequency, self.ctrl_reg1.bandwidth()).map_err(Error::Config)?;
         let reg = self.ctrl_reg2.with_output_rate(frequency);
         self.iface.write_register(reg).await?;
         self.ctrl_reg2 = reg;
@@ -121,6 +189,117 @@ where
         MagMode::Continuous {
             frequency,
             set_period,
+            auto_sr: self.auto_sr(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mock::{init_i2c, sim_with_field, Mmc5983Mock, MockDelay},
+        register_address::{InternalControl0, RegRead},
+        BandwidthMode, ConfigError,
+    };
+
+    #[test]
+    fn auto_sr_round_trips() {
+        let sim = Mmc5983Mock::new();
+        let mut mag = init_i2c(&sim);
+        assert_eq!(mag.get_mode_config(), MagMode::OneShot { auto_sr: false });
+
+        mag.enable_auto_sr().unwrap();
+        assert_eq!(mag.get_mode_config(), MagMode::OneShot { auto_sr: true });
+        assert_ne!(
+            sim.register(InternalControl0::ADDR) & InternalControl0::AUTO_SR.bits(),
+            0
+        );
+
+        // The chip restores SET polarity before measuring
+        sim.set_field((100, 0, 0));
+        mag.reset(&mut MockDelay).unwrap();
+        assert_eq!(mag.get_calibrated_field().unwrap().counts(), (100, 0, 0));
+
+        let mut mag = mag.into_continuous(MagOutputDataRate::Hz10, None).unwrap();
+        assert_eq!(
+            mag.get_mode_config(),
+            MagMode::Continuous {
+                frequency: MagOutputDataRate::Hz10,
+                set_period: None,
+                auto_sr: true
+            }
+        );
+        mag.disable_auto_sr().unwrap();
+        assert!(!mag.auto_sr());
+        assert_eq!(
+            sim.register(InternalControl0::ADDR) & InternalControl0::AUTO_SR.bits(),
+            0
+        );
+    }
+
+    #[test]
+    fn refuses_rate_above_bandwidth() {
+        let sim = Mmc5983Mock::new();
+        let mag = init_i2c(&sim);
+        assert!(matches!(
+            mag.into_continuous(MagOutputDataRate::Hz200, None),
+            Err(Error::Config(ConfigError::BandwidthTooLow {
+                required: BandwidthMode::Hz200,
+                ..
+            }))
+        ));
+        assert_eq!(
+            sim.register(InternalControl2::ADDR) & InternalControl2::CMM_EN.bits(),
+            0
+        );
+
+        let mut mag = init_i2c(&sim);
+        let rate = MagOutputDataRate::Hz1000;
+        mag.set_bandwidth(BandwidthMode::for_output_rate(rate))
+            .unwrap();
+        let mut mag = mag.into_continuous(rate, None).unwrap();
+        assert!(matches!(
+            mag.set_bandwidth(BandwidthMode::Hz400),
+            Err(Error::Config(_))
+        ));
+
+        mag.set_frequency(MagOutputDataRate::Hz50).unwrap();
+        mag.set_bandwidth(BandwidthMode::Hz100).unwrap();
+        assert!(matches!(
+            mag.set_frequency(MagOutputDataRate::Hz200),
+            Err(Error::Config(_))
+        ));
+        assert_eq!(
+            sim.register(InternalControl2::ADDR),
+            InternalControl2::CMM_EN.bits() | 0b100
+        );
+    }
+
+    #[test]
+    fn continuous_mode() {
+        let sim = sim_with_field((0, 100, 0));
+        let mut mag = init_i2c(&sim)
+            .into_continuous(MagOutputDataRate::Hz100, Some(SetResetPeriod::Every25))
+            .unwrap();
+        let reg = sim.register(InternalControl2::ADDR);
+        assert_eq!(reg, 0b1001_1101);
+
+        let before = sim.measurements();
+        let field = mag.magnetic_field().unwrap();
+        assert_eq!(field.counts(), (0, 100, 0));
+        let field = mag
+            .magnetic_field_on_interrupt(&mut sim.int_pin(), &mut MockDelay)
+            .unwrap();
+        assert_eq!(field.counts(), (0, 100, 0));
+        assert!(sim.measurements() > before);
+
+        let mut mag = mag.into_oneshot().unwrap();
+        assert_eq!(sim.register(InternalControl2::ADDR) & 0b1000_1000, 0);
+        let count = sim.measurements();
+        for _ in 0..10 {
+            mag.status().unwrap();
         }
+        assert_eq!(sim.measurements(), count);
     }
 }
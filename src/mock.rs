@@ -0,0 +1,692 @@
+//! Simulated MMC5983 for host testing
+//!
+//! [`Mmc5983Mock`] models the register file from [`register_address`](crate::register_address)
+//! behind the embedded-hal I2C and SPI traits, so the whole driver can run
+//! without hardware:
+//!
+//! ```
+//! use mmc5983_rs::{mock::{Mmc5983Mock, MockDelay}, Mmc5983};
+//!
+//! let sim = Mmc5983Mock::new();
+//! sim.set_field((16384, 0, -16384));
+//!
+//! let mut mag = Mmc5983::new_with_i2c(&sim);
+//! mag.init().unwrap();
+//! mag.calibrate_offset(&mut MockDelay).unwrap();
+//! let field = mag.get_calibrated_field().unwrap();
+//! assert_eq!(field.gauss(), (1.0, 0.0, -1.0));
+//! ```
+//!
+//! Time is simulated in ticks: every bus transaction and every INT pin read
+//! advances the simulation by one tick. A measurement completes after
+//! [`Mmc5983Mock::set_latency`] ticks.
+
+use core::{cell::RefCell, convert::Infallible};
+
+use embedded_hal::{delay, digital, i2c, spi};
+
+#[cfg(test)]
+use crate::{interface::I2cInterface, mode, Mmc5983};
+use crate::{
+    interface::MMC5983_ADDR,
+    register_address::{
+        InternalControl0, InternalControl1, InternalControl2, InternalControl3, ProductId1,
+        RegRead, XYZout2,
+    },
+    types::StatusFlags,
+    Channels, MagneticField, Resolution,
+};
+
+/// Highest output code of the 18-bit converter
+const FULL_SCALE: i32 = (1 << 18) - 1;
+
+/// Default number of ticks a measurement takes
+const DEFAULT_LATENCY: u32 = 3;
+
+/// Polarity of the sensing bridge, as left by the last SET or RESET
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Polarity {
+    /// Magnetized by a SET operation, output is `offset + field`
+    Set,
+    /// Magnetized by a RESET operation, output is `offset - field`
+    Reset,
+}
+
+/// Simulated register file and sensor state
+#[derive(Debug)]
+struct State {
+    outputs: [u8; 8],
+    status: StatusFlags,
+    interrupts: StatusFlags,
+    reserved_status: u8,
+    ctrl0: InternalControl0,
+    ctrl1: InternalControl1,
+    ctrl2: InternalControl2,
+    ctrl3: InternalControl3,
+    polarity: Polarity,
+    field: (i32, i32, i32),
+    offset: (i32, i32, i32),
+    self_test: i32,
+    temperature: u8,
+    latency: u32,
+    stalled: bool,
+    pending_meas: Option<u32>,
+    pending_temp: Option<u32>,
+    next_continuous: u32,
+    measurements: u32,
+}
+
+impl State {
+    const fn new() -> Self {
+        Self {
+            outputs: [0; 8],
+            status: StatusFlags::OTP_READ_DONE,
+            interrupts: StatusFlags::empty(),
+            reserved_status: 0,
+            ctrl0: InternalControl0::empty(),
+            ctrl1: InternalControl1::empty(),
+            ctrl2: InternalControl2::empty(),
+            ctrl3: InternalControl3::empty(),
+            polarity: Polarity::Set,
+            field: (0, 0, 0),
+            offset: (0, 0, 0),
+            self_test: 0,
+            temperature: 0,
+            latency: DEFAULT_LATENCY,
+            stalled: false,
+            pending_meas: None,
+            pending_temp: None,
+            next_continuous: DEFAULT_LATENCY,
+            measurements: 0,
+        }
+    }
+
+    /// Power-on/software reset: clear registers but keep the environment
+    fn reset(&mut self) {
+        *self = Self {
+            field: self.field,
+            offset: self.offset,
+            self_test: self.self_test,
+            temperature: self.temperature,
+            latency: self.latency,
+            stalled: self.stalled,
+            reserved_status: self.reserved_status,
+            measurements: self.measurements,
+            ..Self::new()
+        };
+    }
+
+    fn continuous(&self) -> bool {
+        self.ctrl2.contains(InternalControl2::CMM_EN)
+            && !self
+                .ctrl2
+                .intersection(InternalControl2::CM_FREQ)
+                .is_empty()
+    }
+
+    /// Advance simulated time by one tick
+    fn tick(&mut self) {
+        if self.stalled {
+            return;
+        }
+        if let Some(remaining) = self.pending_meas {
+            if remaining <= 1 {
+                self.pending_meas = None;
+                self.complete_meas();
+            } else {
+                self.pending_meas = Some(remaining - 1);
+            }
+        }
+        if let Some(remaining) = self.pending_temp {
+            if remaining <= 1 {
+                self.pending_temp = None;
+                self.outputs[7] = self.temperature;
+                self.complete(StatusFlags::MEAS_T_DONE);
+            } else {
+                self.pending_temp = Some(remaining - 1);
+            }
+        }
+        if self.continuous() {
+            if self.next_continuous <= 1 {
+                self.next_continuous = self.latency.max(1);
+                self.complete_meas();
+            } else {
+                self.next_continuous -= 1;
+            }
+        }
+    }
+
+    fn complete(&mut self, flag: StatusFlags) {
+        self.status |= flag;
+        if self.ctrl0.contains(InternalControl0::INT_MEAS_DONE_EN) {
+            self.interrupts |= flag;
+        }
+    }
+
+    fn complete_meas(&mut self) {
+        let periodic_set = self.continuous()
+            && self.ctrl2.contains(InternalControl2::EN_PRD_SET)
+            && self.measurements.is_multiple_of(period_len(self.ctrl2));
+        if periodic_set || self.ctrl0.contains(InternalControl0::AUTO_SR) {
+            self.polarity = Polarity::Set;
+        }
+
+        let self_test = if self.ctrl3.contains(InternalControl3::ST_ENP) {
+            self.self_test
+        } else if self.ctrl3.contains(InternalControl3::ST_ENM) {
+            -self.self_test
+        } else {
+            0
+        };
+        let output = |field: i32, offset: i32| -> u32 {
+            let field = field + self_test;
+            let field = match self.polarity {
+                Polarity::Set => field,
+                Polarity::Reset => -field,
+            };
+            (MagneticField::NULL_FIELD as i32 + offset + field).clamp(0, FULL_SCALE) as u32
+        };
+        let x = output(self.field.0, self.offset.0);
+        let y = output(self.field.1, self.offset.1);
+        let z = output(self.field.2, self.offset.2);
+
+        let mut xyz2 = self.outputs[6];
+        if !self.ctrl1.contains(InternalControl1::X_INHIBIT) {
+            self.outputs[0] = (x >> 10) as u8;
+            self.outputs[1] = (x >> 2) as u8;
+            xyz2 = (xyz2 & !0b1100_0000) | ((x as u8 & 0b11) << 6);
+        }
+        if !self.ctrl1.contains(InternalControl1::YZ_INHIBIT) {
+            self.outputs[2] = (y >> 10) as u8;
+            self.outputs[3] = (y >> 2) as u8;
+            self.outputs[4] = (z >> 10) as u8;
+            self.outputs[5] = (z >> 2) as u8;
+            xyz2 = (xyz2 & !0b0011_1100) | ((y as u8 & 0b11) << 4) | ((z as u8 & 0b11) << 2);
+        }
+        self.outputs[6] = xyz2;
+
+        self.measurements = self.measurements.wrapping_add(1);
+        self.complete(StatusFlags::MEAS_M_DONE);
+    }
+
+    fn read(&mut self, addr: u8) -> u8 {
+        match addr {
+            0x00..=0x07 => self.outputs[addr as usize],
+            StatusFlags::ADDR => self.status.bits() | self.reserved_status,
+            ProductId1::ADDR => ProductId1::ID,
+            // Control registers are write-only
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, addr: u8, data: u8) {
+        match addr {
+            StatusFlags::ADDR => {
+                self.interrupts = self
+                    .interrupts
+                    .difference(StatusFlags::from_bits_truncate(data));
+            }
+            InternalControl0::ADDR => {
+                let reg = InternalControl0::from_bits_truncate(data);
+                if reg.contains(InternalControl0::SET) {
+                    self.polarity = Polarity::Set;
+                }
+                if reg.contains(InternalControl0::RESET) {
+                    self.polarity = Polarity::Reset;
+                }
+                if reg.contains(InternalControl0::OTP_READ) {
+                    self.status |= StatusFlags::OTP_READ_DONE;
+                }
+                // A trigger while a measurement is in progress is ignored
+                if reg.contains(InternalControl0::TM_M) && self.pending_meas.is_none() {
+                    self.status.remove(StatusFlags::MEAS_M_DONE);
+                    self.pending_meas = Some(self.latency);
+                }
+                if reg.contains(InternalControl0::TM_T) && self.pending_temp.is_none() {
+                    self.status.remove(StatusFlags::MEAS_T_DONE);
+                    self.pending_temp = Some(self.latency);
+                }
+                self.ctrl0 = reg
+                    .intersection(InternalControl0::INT_MEAS_DONE_EN | InternalControl0::AUTO_SR);
+            }
+            InternalControl1::ADDR => {
+                let reg = InternalControl1::from_bits_truncate(data);
+                if reg.contains(InternalControl1::SW_RST) {
+                    self.reset();
+                } else {
+                    self.ctrl1 = reg;
+                }
+            }
+            InternalControl2::ADDR => {
+                self.ctrl2 = InternalControl2::from_bits_truncate(data);
+                self.next_continuous = self.latency.max(1);
+            }
+            InternalControl3::ADDR => {
+                self.ctrl3 = InternalControl3::from_bits_truncate(data);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Number of measurements between periodic SET operations
+fn period_len(reg: InternalControl2) -> u32 {
+    const PERIODS: [u32; 8] = [1, 25, 75, 100, 250, 500, 1000, 2000];
+    PERIODS[reg.set_period() as usize]
+}
+
+/// Simulated MMC5983 device
+///
+/// The I2C and SPI traits are implemented for `&Mmc5983Mock`, so the
+/// simulation can be inspected and steered while a driver owns the bus.
+#[derive(Debug)]
+pub struct Mmc5983Mock {
+    state: RefCell<State>,
+}
+
+impl Default for Mmc5983Mock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Mmc5983Mock {
+    /// Create a new simulated device in its power-on state
+    pub const fn new() -> Self {
+        Self {
+            state: RefCell::new(State::new()),
+        }
+    }
+
+    /// Set the external magnetic field, in signed counts
+    pub fn set_field(&self, field: (i32, i32, i32)) {
+        self.state.borrow_mut().field = field;
+    }
+
+    /// Set the bridge offset added to every measurement, in signed counts
+    pub fn set_bridge_offset(&self, offset: (i32, i32, i32)) {
+        self.state.borrow_mut().offset = offset;
+    }
+
+    /// Set the field created by the self-test coil on each axis, in counts
+    pub fn set_self_test_field(&self, field: i32) {
+        self.state.borrow_mut().self_test = field;
+    }
+
+    /// Set the raw temperature reported by the next temperature measurement
+    pub fn set_temperature(&self, raw: u8) {
+        self.state.borrow_mut().temperature = raw;
+    }
+
+    /// Set the number of ticks a measurement takes
+    pub fn set_latency(&self, ticks: u32) {
+        self.state.borrow_mut().latency = ticks;
+    }
+
+    /// Report `bits` as set in the reserved bits of the status register
+    pub fn set_reserved_status(&self, bits: u8) {
+        self.state.borrow_mut().reserved_status = bits & !StatusFlags::all().bits();
+    }
+
+    /// Freeze the simulation so that no measurement ever completes
+    pub fn set_stalled(&self, stalled: bool) {
+        self.state.borrow_mut().stalled = stalled;
+    }
+
+    /// Return all registers to their power-on defaults, as after a brown-out.
+    /// The simulated environment is kept.
+    pub fn power_cycle(&self) {
+        self.state.borrow_mut().reset();
+    }
+
+    /// Current bridge polarity
+    pub fn polarity(&self) -> Polarity {
+        self.state.borrow().polarity
+    }
+
+    /// Number of magnetic measurements completed so far
+    pub fn measurements(&self) -> u32 {
+        self.state.borrow().measurements
+    }
+
+    /// Last value written to a control register, or the content of any
+    /// other register, without advancing the simulation.
+    pub fn register(&self, addr: u8) -> u8 {
+        let mut state = self.state.borrow_mut();
+        match addr {
+            InternalControl0::ADDR => state.ctrl0.bits(),
+            InternalControl1::ADDR => state.ctrl1.bits(),
+            InternalControl2::ADDR => state.ctrl2.bits(),
+            InternalControl3::ADDR => state.ctrl3.bits(),
+            _ => state.read(addr),
+        }
+    }
+
+    /// Raw magnetic field currently held in the output registers
+    pub fn output(&self) -> MagneticField {
+        let state = self.state.borrow();
+        let o = &state.outputs;
+        let xyz2 = XYZout2::from_data(o[6]);
+        let axis =
+            |msb: u8, lsb: u8, bits: u8| ((msb as u32) << 10) | ((lsb as u32) << 2) | bits as u32;
+        MagneticField {
+            x: axis(o[0], o[1], xyz2.x_bits()),
+            y: axis(o[2], o[3], xyz2.y_bits()),
+            z: axis(o[4], o[5], xyz2.z_bits()),
+            resolution: Resolution::Bits18,
+            channels: Channels::Xyz,
+        }
+    }
+
+    /// INT pin driven by the simulated device
+    pub fn int_pin(&self) -> MockIntPin<'_> {
+        MockIntPin { sim: self }
+    }
+
+    /// Level of the INT pin
+    pub fn interrupt(&self) -> bool {
+        !self.state.borrow().interrupts.is_empty()
+    }
+
+    fn i2c_transaction(
+        &self,
+        address: u8,
+        operations: &mut [i2c::Operation<'_>],
+    ) -> Result<(), i2c::ErrorKind> {
+        if address != MMC5983_ADDR {
+            return Err(i2c::ErrorKind::NoAcknowledge(
+                i2c::NoAcknowledgeSource::Address,
+            ));
+        }
+        let mut state = self.state.borrow_mut();
+        state.tick();
+        let mut pointer = None;
+        for op in operations {
+            match op {
+                i2c::Operation::Write(bytes) => {
+                    for &byte in bytes.iter() {
+                        match pointer {
+                            None => pointer = Some(byte),
+                            Some(addr) => {
+                                state.write(addr, byte);
+                                pointer = Some(addr + 1);
+                            }
+                        }
+                    }
+                }
+                i2c::Operation::Read(buffer) => {
+                    let mut addr = pointer.unwrap_or(0);
+                    for byte in buffer.iter_mut() {
+                        *byte = state.read(addr);
+                        addr += 1;
+                    }
+                    pointer = Some(addr);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn spi_transaction(
+        &self,
+        operations: &mut [spi::Operation<'_, u8>],
+    ) -> Result<(), spi::ErrorKind> {
+        let mut state = self.state.borrow_mut();
+        state.tick();
+        // First byte is the command: R/W bit followed by the register address
+        let mut command: Option<(bool, u8)> = None;
+        // Reads on a full-duplex transfer see the idle MISO line in 3-wire
+        // mode, since the chip drives SDIO instead of SDO
+        let mut exchange = |out: u8, full_duplex: bool| -> u8 {
+            match command {
+                None => {
+                    command = Some((out & 0x80 != 0, out & 0x3F));
+                    0
+                }
+                Some((true, addr)) => {
+                    command = Some((true, addr + 1));
+                    let data = state.read(addr);
+                    if full_duplex && state.ctrl3.contains(InternalControl3::SPI_3W) {
+                        0xFF
+                    } else {
+                        data
+                    }
+                }
+                Some((false, addr)) => {
+                    command = Some((false, addr + 1));
+                    state.write(addr, out);
+                    0
+                }
+            }
+        };
+        for op in operations {
+            match op {
+                spi::Operation::Write(bytes) => {
+                    for &byte in bytes.iter() {
+                        exchange(byte, false);
+                    }
+                }
+                spi::Operation::Read(buffer) => {
+                    for byte in buffer.iter_mut() {
+                        *byte = exchange(0, false);
+                    }
+                }
+                spi::Operation::Transfer(read, write) => {
+                    for i in 0..read.len().max(write.len()) {
+                        let input = exchange(write.get(i).copied().unwrap_or(0), true);
+                        if let Some(byte) = read.get_mut(i) {
+                            *byte = input;
+                        }
+                    }
+                }
+                spi::Operation::TransferInPlace(buffer) => {
+                    for byte in buffer.iter_mut() {
+                        *byte = exchange(*byte, true);
+                    }
+                }
+                spi::Operation::DelayNs(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn pin_is_high(&self) -> bool {
+        let mut state = self.state.borrow_mut();
+        state.tick();
+        !state.interrupts.is_empty()
+    }
+}
+
+impl i2c::ErrorType for &Mmc5983Mock {
+    type Error = i2c::ErrorKind;
+}
+
+impl i2c::I2c for &Mmc5983Mock {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.i2c_transaction(address, operations)
+    }
+}
+
+impl spi::ErrorType for &Mmc5983Mock {
+    type Error = spi::ErrorKind;
+}
+
+impl spi::SpiDevice for &Mmc5983Mock {
+    fn transaction(
+        &mut self,
+        operations: &mut [spi::Operation<'_, u8>],
+    ) -> Result<(), Self::Error> {
+        self.spi_transaction(operations)
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::i2c::I2c for &Mmc5983Mock {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.i2c_transaction(address, operations)
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::spi::SpiDevice for &Mmc5983Mock {
+    async fn transaction(
+        &mut self,
+        operations: &mut [spi::Operation<'_, u8>],
+    ) -> Result<(), Self::Error> {
+        self.spi_transaction(operations)
+    }
+}
+
+/// INT pin of a [`Mmc5983Mock`]
+#[derive(Debug)]
+pub struct MockIntPin<'a> {
+    sim: &'a Mmc5983Mock,
+}
+
+impl digital::ErrorType for MockIntPin<'_> {
+    type Error = Infallible;
+}
+
+impl digital::InputPin for MockIntPin<'_> {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.sim.pin_is_high())
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.sim.pin_is_high())
+    }
+}
+
+#[cfg(feature = "async")]
+impl MockIntPin<'_> {
+    /// Advance the simulation until the pin reaches `level`
+    async fn wait_for_level(&mut self, level: bool) {
+        core::future::poll_fn(|cx| {
+            if self.sim.pin_is_high() == level {
+                core::task::Poll::Ready(())
+            } else {
+                cx.waker().wake_by_ref();
+                core::task::Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::digital::Wait for MockIntPin<'_> {
+    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+        self.wait_for_level(true).await;
+        Ok(())
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+        self.wait_for_level(false).await;
+        Ok(())
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+        self.wait_for_level(false).await;
+        self.wait_for_level(true).await;
+        Ok(())
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+        self.wait_for_level(true).await;
+        self.wait_for_level(false).await;
+        Ok(())
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+        let level = self.sim.pin_is_high();
+        self.wait_for_level(!level).await;
+        Ok(())
+    }
+}
+
+/// Delay provider that does not wait
+///
+/// Blocking delays return immediately. Async delays return after being
+/// polled once per [`MockDelay::POLL_NS`] of delay, so that a future raced
+/// against a timeout, like an INT pin wait, still gets polled a bounded
+/// number of times.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MockDelay;
+
+impl MockDelay {
+    /// Simulated duration of one poll of an async delay
+    pub const POLL_NS: u32 = 100_000;
+}
+
+impl delay::DelayNs for MockDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::delay::DelayNs for MockDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        let mut polls = ns / Self::POLL_NS;
+        core::future::poll_fn(|cx| {
+            if polls == 0 {
+                return core::task::Poll::Ready(());
+            }
+            polls -= 1;
+            cx.waker().wake_by_ref();
+            core::task::Poll::Pending
+        })
+        .await
+    }
+}
+
+/// Simulated device measuring `field`, for driver tests
+#[cfg(test)]
+pub(crate) fn sim_with_field(field: (i32, i32, i32)) -> Mmc5983Mock {
+    let sim = Mmc5983Mock::new();
+    sim.set_field(field);
+    sim
+}
+
+/// Driver on the I2C bus of `sim`, initialized with the default config
+#[cfg(test)]
+pub(crate) fn init_i2c(sim: &Mmc5983Mock) -> Mmc5983<I2cInterface<&Mmc5983Mock>, mode::OneShot> {
+    let mut mag = Mmc5983::new_with_i2c(sim);
+    mag.init().unwrap();
+    mag
+}
+
+/// Run `future` to completion, for async driver tests on the mock which
+/// never needs a real waker
+#[cfg(all(test, feature = "async"))]
+pub(crate) fn block_on<F: core::future::Future>(future: F) -> F::Output {
+    let mut future = core::pin::pin!(future);
+    let mut cx = core::task::Context::from_waker(core::task::Waker::noop());
+    loop {
+        if let core::task::Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrong_address_is_not_acknowledged() {
+        let sim = Mmc5983Mock::new();
+        let mut bus = &sim;
+        let err = i2c::I2c::write(&mut bus, MMC5983_ADDR + 1, &[0x2F]).unwrap_err();
+        assert_eq!(
+            err,
+            i2c::ErrorKind::NoAcknowledge(i2c::NoAcknowledgeSource::Address)
+        );
+    }
+}
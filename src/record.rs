@@ -0,0 +1,367 @@
+//! Sample records for logging
+//!
+//! A [`Record`] is one timestamped measurement: the raw counts, the field in
+//! Gauss and optionally the temperature. It is written as a CSV line or a
+//! JSON-lines object into any [`core::fmt::Write`] sink, without allocating.
+//! Axes excluded by the measured [`Channels`](crate::Channels) are left empty
+//! in CSV and written as `null` in JSON.
+//!
+//! ```
+//! use mmc5983_rs::{record::{Record, RecordFormat}, MagneticField};
+//! # let field = MagneticField::default().centred();
+//!
+//! let mut line = String::new();
+//! RecordFormat::Csv.write_header(&mut line).unwrap();
+//! Record::new(1_000, field).write(&mut line, RecordFormat::Csv).unwrap();
+//! ```
+//!
+//! For reproducing problems, [`Snapshot`]s keep the raw register contents
+//! from `Xout0` to `Status` in a compact binary format. A [`Recording`] of
+//! them can be fed back through the driver with
+//! [`ReplayInterface`](crate::interface::ReplayInterface):
+//!
+//! ```
+//! use mmc5983_rs::{record::{Recording, Snapshot, RECORDING_HEADER}, Mmc5983};
+//!
+//! // Zero field on all axes, measurement done
+//! let snapshot = Snapshot::new(0, [0x80, 0, 0x80, 0, 0x80, 0, 0, 0, 0x01]);
+//! let mut data = Vec::from(RECORDING_HEADER);
+//! data.extend(snapshot.to_bytes());
+//!
+//! let mut mag = Mmc5983::new_with_replay(Recording::new(&data).unwrap());
+//! mag.init().unwrap();
+//! assert_eq!(mag.get_calibrated_field().unwrap().counts(), (0, 0, 0));
+//! ```
+
+use core::fmt::{self, Write};
+
+use crate::{MagneticFieldI32, Temperature};
+
+/// Output format of a [`Record`]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RecordFormat {
+    /// Comma separated values, one line per record
+    #[default]
+    Csv,
+    /// One JSON object per line
+    JsonLines,
+}
+
+impl RecordFormat {
+    /// Write the CSV column names. JSON-lines has no header, nothing is
+    /// written.
+    pub fn write_header<W: Write>(&self, w: &mut W) -> fmt::Result {
+        match self {
+            RecordFormat::Csv => writeln!(
+                w,
+                "timestamp_us,x_raw,y_raw,z_raw,x_gauss,y_gauss,z_gauss,temperature_c"
+            ),
+            RecordFormat::JsonLines => Ok(()),
+        }
+    }
+}
+
+/// A timestamped measurement
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Record {
+    timestamp_us: u64,
+    field: MagneticFieldI32,
+    temperature: Option<Temperature>,
+}
+
+impl Record {
+    /// Create a record of `field` taken at `timestamp_us`
+    pub const fn new(timestamp_us: u64, field: MagneticFieldI32) -> Self {
+        Self {
+            timestamp_us,
+            field,
+            temperature: None,
+        }
+    }
+
+    /// Add the temperature measured with the field
+    pub const fn with_temperature(self, temperature: Temperature) -> Self {
+        Self {
+            temperature: Some(temperature),
+            ..self
+        }
+    }
+
+    /// Timestamp in microseconds
+    pub const fn timestamp_us(&self) -> u64 {
+        self.timestamp_us
+    }
+
+    /// Measured magnetic field
+    pub const fn field(&self) -> MagneticFieldI32 {
+        self.field
+    }
+
+    /// Temperature, if recorded
+    pub const fn temperature(&self) -> Option<Temperature> {
+        self.temperature
+    }
+
+    /// Raw counts of the measured axes
+    fn raw(&self) -> [Option<u32>; 3] {
+        let raw = self.field.raw();
+        let channels = self.field.channels();
+        [
+            channels.x().then(|| raw.x_raw()),
+            channels.yz().then(|| raw.y_raw()),
+            channels.yz().then(|| raw.z_raw()),
+        ]
+    }
+
+    /// Write the record as one line in `format`
+    pub fn write<W: Write>(&self, w: &mut W, format: RecordFormat) -> fmt::Result {
+        let raw = self.raw();
+        let (x, y, z) = self.field.checked_gauss();
+        let gauss = [x, y, z];
+        let temperature = self.temperature.map(|t| t.degrees_celsius());
+
+        match format {
+            RecordFormat::Csv => {
+                write!(w, "{}", self.timestamp_us)?;
+                for value in raw {
+                    w.write_char(',')?;
+                    if let Some(value) = value {
+                        write!(w, "{value}")?;
+                    }
+                }
+                for value in gauss.into_iter().chain([temperature]) {
+                    w.write_char(',')?;
+                    if let Some(value) = value {
+                        write!(w, "{value:.6}")?;
+                    }
+                }
+            }
+            RecordFormat::JsonLines => {
+                write!(w, "{{\"timestamp_us\":{}", self.timestamp_us)?;
+                for (name, value) in ["x_raw", "y_raw", "z_raw"].into_iter().zip(raw) {
+                    match value {
+                        Some(value) => write!(w, ",\"{name}\":{value}")?,
+                        None => write!(w, ",\"{name}\":null")?,
+                    }
+                }
+                let names = ["x_gauss", "y_gauss", "z_gauss", "temperature_c"];
+                for (name, value) in names
+                    .into_iter()
+                    .zip(gauss.into_iter().chain([temperature]))
+                {
+                    match value {
+                        Some(value) => write!(w, ",\"{name}\":{value:.6}")?,
+                        None => write!(w, ",\"{name}\":null")?,
+                    }
+                }
+                w.write_char('}')?;
+            }
+        }
+        w.write_char('\n')
+    }
+}
+
+/// Start of a binary recording: magic bytes and format version
+pub const RECORDING_HEADER: [u8; 5] = *b"MMC5\x01";
+
+/// Encoded size of a [`Snapshot`] in bytes
+pub const SNAPSHOT_LEN: usize = 8 + SNAPSHOT_REGISTERS;
+
+/// Number of registers in a snapshot, `Xout0` (0x00) to `Status` (0x08)
+pub const SNAPSHOT_REGISTERS: usize = 9;
+
+/// Invalid or exhausted binary recordings
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RecordingError {
+    /// The data does not start with [`RECORDING_HEADER`]
+    InvalidHeader,
+    /// The data ends in the middle of a snapshot
+    Truncated,
+    /// A replay read past the last snapshot
+    EndOfRecording,
+}
+
+/// Timestamped raw register contents from `Xout0` to `Status`
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Snapshot {
+    timestamp_us: u64,
+    registers: [u8; SNAPSHOT_REGISTERS],
+}
+
+impl Snapshot {
+    /// Create a snapshot of the registers starting at `Xout0`
+    pub const fn new(timestamp_us: u64, registers: [u8; SNAPSHOT_REGISTERS]) -> Self {
+        Self {
+            timestamp_us,
+            registers,
+        }
+    }
+
+    /// Timestamp in microseconds
+    pub const fn timestamp_us(&self) -> u64 {
+        self.timestamp_us
+    }
+
+    /// Register contents, indexed by address
+    pub const fn registers(&self) -> [u8; SNAPSHOT_REGISTERS] {
+        self.registers
+    }
+
+    /// Encode as little-endian timestamp followed by the registers
+    pub fn to_bytes(&self) -> [u8; SNAPSHOT_LEN] {
+        let mut bytes = [0; SNAPSHOT_LEN];
+        bytes[..8].copy_from_slice(&self.timestamp_us.to_le_bytes());
+        bytes[8..].copy_from_slice(&self.registers);
+        bytes
+    }
+
+    /// Decode a snapshot encoded by [`to_bytes`](Self::to_bytes)
+    pub fn from_bytes(bytes: &[u8; SNAPSHOT_LEN]) -> Self {
+        let mut timestamp = [0; 8];
+        timestamp.copy_from_slice(&bytes[..8]);
+        let mut registers = [0; SNAPSHOT_REGISTERS];
+        registers.copy_from_slice(&bytes[8..]);
+        Self::new(u64::from_le_bytes(timestamp), registers)
+    }
+}
+
+/// A binary recording: [`RECORDING_HEADER`] followed by encoded snapshots
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Recording<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Recording<'a> {
+    /// Check the header and length of `data`
+    pub fn new(data: &'a [u8]) -> Result<Self, RecordingError> {
+        let data = data
+            .strip_prefix(&RECORDING_HEADER)
+            .ok_or(RecordingError::InvalidHeader)?;
+        if !data.len().is_multiple_of(SNAPSHOT_LEN) {
+            return Err(RecordingError::Truncated);
+        }
+        Ok(Self { data })
+    }
+
+    /// Number of snapshots
+    pub fn len(&self) -> usize {
+        self.data.len() / SNAPSHOT_LEN
+    }
+
+    /// Whether the recording holds no snapshots
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Iterate over the snapshots
+    pub fn snapshots(&self) -> impl Iterator<Item = Snapshot> + 'a {
+        self.data.chunks_exact(SNAPSHOT_LEN).map(|chunk| {
+            let mut bytes = [0; SNAPSHOT_LEN];
+            bytes.copy_from_slice(chunk);
+            Snapshot::from_bytes(&bytes)
+        })
+    }
+
+    /// Remove and return the first snapshot
+    pub(crate) fn pop(&mut self) -> Option<Snapshot> {
+        let snapshot = self.snapshots().next()?;
+        self.data = &self.data[SNAPSHOT_LEN..];
+        Some(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::string::String;
+
+    use super::*;
+    use crate::{Channels, MagneticField};
+
+    fn field(channels: Channels) -> MagneticFieldI32 {
+        let null = MagneticField::NULL_FIELD;
+        let raw = MagneticField {
+            x: null + 16384,
+            y: null - 8192,
+            z: null,
+            channels,
+            ..MagneticField::default()
+        };
+        raw.with_inhibited_nulled().centred()
+    }
+
+    #[test]
+    fn csv_lines() {
+        let mut out = String::new();
+        RecordFormat::Csv.write_header(&mut out).unwrap();
+        Record::new(1_500, field(Channels::Xyz))
+            .with_temperature(Temperature { raw: 100 })
+            .write(&mut out, RecordFormat::Csv)
+            .unwrap();
+        Record::new(2_500, field(Channels::X))
+            .write(&mut out, RecordFormat::Csv)
+            .unwrap();
+
+        let mut lines = out.lines();
+        assert_eq!(
+            lines.next(),
+            Some("timestamp_us,x_raw,y_raw,z_raw,x_gauss,y_gauss,z_gauss,temperature_c")
+        );
+        assert_eq!(
+            lines.next(),
+            Some("1500,147456,122880,131072,1.000000,-0.500000,0.000000,5.000000")
+        );
+        assert_eq!(lines.next(), Some("2500,147456,,,1.000000,,,"));
+        assert_eq!(lines.next(), None);
+    }
+
+    #[test]
+    fn json_lines() {
+        let mut out = String::new();
+        RecordFormat::JsonLines.write_header(&mut out).unwrap();
+        Record::new(7, field(Channels::Yz))
+            .write(&mut out, RecordFormat::JsonLines)
+            .unwrap();
+        assert_eq!(
+            out,
+            "{\"timestamp_us\":7,\"x_raw\":null,\"y_raw\":122880,\"z_raw\":131072,\
+             \"x_gauss\":null,\"y_gauss\":-0.500000,\"z_gauss\":0.000000,\
+             \"temperature_c\":null}\n"
+        );
+    }
+
+    #[test]
+    fn binary_recording() {
+        let snapshot = Snapshot::new(0x0102_0304_0506, [1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        let bytes = snapshot.to_bytes();
+        assert_eq!(bytes[..8], [6, 5, 4, 3, 2, 1, 0, 0]);
+        assert_eq!(Snapshot::from_bytes(&bytes), snapshot);
+
+        let mut data = std::vec::Vec::from(RECORDING_HEADER);
+        data.extend(bytes);
+        data.extend(Snapshot::default().to_bytes());
+        let mut recording = Recording::new(&data).unwrap();
+        assert_eq!(recording.len(), 2);
+        assert_eq!(recording.pop(), Some(snapshot));
+        assert_eq!(recording.pop(), Some(Snapshot::default()));
+        assert_eq!(recording.pop(), None);
+        assert!(recording.is_empty());
+
+        assert_eq!(
+            Recording::new(&data[1..]),
+            Err(RecordingError::InvalidHeader)
+        );
+        assert_eq!(
+            Recording::new(&data[..data.len() - 1]),
+            Err(RecordingError::Truncated)
+        );
+    }
+}
@@ -1,4 +1,6 @@
-use crate::types::{BandwidthMode, MagOutputDataRate, ProductId, SetResetPeriod, StatusFlags};
+use crate::types::{
+    BandwidthMode, Channels, MagOutputDataRate, ProductId, SetResetPeriod, StatusFlags,
+};
 
 /// Trait for reading from registers
 pub trait RegRead<D = u8> {
@@ -145,9 +147,21 @@ register! {
     pub type Tout: 0x07 = u8;
 }
 
-register! {
-    /// Status register
-    pub type Status: 0x08 = StatusFlags;
+/// Status register read with its reserved bits, so that they can be reported.
+impl RegRead for StatusFlags {
+    type Output = Self;
+    const ADDR: u8 = 0x08;
+
+    fn from_data(data: u8) -> Self::Output {
+        Self::from_bits_retain(data)
+    }
+}
+
+/// Writing 1s to the status register clears the corresponding interrupts.
+impl RegWrite for StatusFlags {
+    fn data(&self) -> u8 {
+        self.bits()
+    }
 }
 
 register! {
@@ -164,13 +178,22 @@ register! {
     }
 }
 
+impl InternalControl0 {
+    /// Bits that the chip clears by itself once the operation is done
+    pub const SELF_CLEARING: Self = Self::OTP_READ
+        .union(Self::RESET)
+        .union(Self::SET)
+        .union(Self::TM_T)
+        .union(Self::TM_M);
+}
+
 register! {
     /// Internal Control 1 register
     #[derive(Debug, Default, Copy, Clone)]
     pub struct InternalControl1: 0x0A {
         const SW_RST = 0b10000000;
-        const YZ_INHIBIT = 0b00110000;
-        const X_INHIBIT = 0b00001000;
+        const YZ_INHIBIT = 0b00011000;
+        const X_INHIBIT = 0b00000100;
         const BW1 = 0b00000010;
         const BW0 = 0b00000001;
 
@@ -179,10 +202,43 @@ register! {
 }
 
 impl InternalControl1 {
+    /// Bits that the chip clears by itself once the operation is done
+    pub const SELF_CLEARING: Self = Self::SW_RST;
+
     pub const fn with_bandwidth(self, bw: BandwidthMode) -> Self {
         let reg = self.difference(Self::BW);
         Self::from_bits_truncate(reg.bits() | (bw as u8))
     }
+
+    /// Get current bandwidth
+    pub const fn bandwidth(&self) -> BandwidthMode {
+        match self.intersection(Self::BW).bits() {
+            0 => BandwidthMode::Hz100,
+            1 => BandwidthMode::Hz200,
+            2 => BandwidthMode::Hz400,
+            _ => BandwidthMode::Hz800,
+        }
+    }
+
+    pub const fn with_channels(self, channels: Channels) -> Self {
+        let reg = self.difference(Self::X_INHIBIT.union(Self::YZ_INHIBIT));
+        match channels {
+            Channels::Xyz => reg,
+            Channels::X => reg.union(Self::YZ_INHIBIT),
+            Channels::Yz => reg.union(Self::X_INHIBIT),
+        }
+    }
+
+    /// Get current channel configuration
+    pub const fn channels(&self) -> Channels {
+        if self.contains(Self::X_INHIBIT) {
+            Channels::Yz
+        } else if self.contains(Self::YZ_INHIBIT) {
+            Channels::X
+        } else {
+            Channels::Xyz
+        }
+    }
 }
 
 register! {
@@ -190,10 +246,10 @@ register! {
     #[derive(Debug, Default, Copy, Clone)]
     pub struct InternalControl2: 0x0B {
         const EN_PRD_SET = 0b10000000;
-        const PRD_SET2 = 0b00100000;
-        const PRD_SET1 = 0b00010000;
-        const PRD_SET0 = 0b00001000;
-        const CMM_EN = 0b00000100;
+        const PRD_SET2 = 0b01000000;
+        const PRD_SET1 = 0b00100000;
+        const PRD_SET0 = 0b00010000;
+        const CMM_EN = 0b00001000;
         const CM_FREQ2 = 0b00000100;
         const CM_FREQ1 = 0b00000010;
         const CM_FREQ0 = 0b00000001;
@@ -206,7 +262,7 @@ register! {
 impl InternalControl2 {
     pub fn with_set_period(self, period: SetResetPeriod) -> Self {
         let reg = self.difference(Self::PRD_SET);
-        Self::from_bits_truncate(reg.bits() | ((period as u8) << 3))
+        Self::from_bits_truncate(reg.bits() | ((period as u8) << 4))
     }
 
     pub fn with_output_rate(self, rate: MagOutputDataRate) -> Self {
@@ -230,7 +286,7 @@ impl InternalControl2 {
 
     /// Get current SET/RESET period configuration
     pub fn set_period(&self) -> SetResetPeriod {
-        let bits = (self.intersection(Self::PRD_SET).bits() >> 3) & 0b111;
+        let bits = (self.intersection(Self::PRD_SET).bits() >> 4) & 0b111;
         match bits {
             0 => SetResetPeriod::Every1,
             1 => SetResetPeriod::Every25,
@@ -250,8 +306,8 @@ register! {
     #[derive(Debug, Default, Copy, Clone)]
     pub struct InternalControl3: 0x0C {
         const SPI_3W = 0b01000000;
-        const ST_ENM = 0b00000010;
-        const ST_ENP = 0b00000001;
+        const ST_ENM = 0b00000100;
+        const ST_ENP = 0b00000010;
     }
 }
 
@@ -263,3 +319,59 @@ register! {
 impl ProductId1 {
     pub(crate) const ID: u8 = 0x30;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Channels;
+
+    // Expected bytes are taken from the datasheet register map, not from the
+    // constants above, so that a wrong bit position cannot cancel out.
+
+    #[test]
+    fn control0_bits() {
+        assert_eq!(InternalControl0::TM_M.bits(), 0x01);
+        assert_eq!(InternalControl0::TM_T.bits(), 0x02);
+        assert_eq!(InternalControl0::INT_MEAS_DONE_EN.bits(), 0x04);
+        assert_eq!(InternalControl0::SET.bits(), 0x08);
+        assert_eq!(InternalControl0::RESET.bits(), 0x10);
+        assert_eq!(InternalControl0::AUTO_SR.bits(), 0x20);
+        assert_eq!(InternalControl0::OTP_READ.bits(), 0x40);
+    }
+
+    #[test]
+    fn control1_bits() {
+        let reg = InternalControl1::default();
+        assert_eq!(InternalControl1::SW_RST.bits(), 0x80);
+        assert_eq!(reg.with_channels(Channels::X).bits(), 0x18);
+        assert_eq!(reg.with_channels(Channels::Yz).bits(), 0x04);
+        assert_eq!(reg.with_bandwidth(BandwidthMode::Hz200).bits(), 0x01);
+        assert_eq!(reg.with_bandwidth(BandwidthMode::Hz800).bits(), 0x03);
+    }
+
+    #[test]
+    fn control2_bits() {
+        let reg = InternalControl2::default();
+        assert_eq!(InternalControl2::CMM_EN.bits(), 0x08);
+        assert_eq!(InternalControl2::EN_PRD_SET.bits(), 0x80);
+        assert_eq!(reg.with_set_period(SetResetPeriod::Every25).bits(), 0x10);
+        assert_eq!(reg.with_set_period(SetResetPeriod::Every2000).bits(), 0x70);
+        assert_eq!(reg.with_output_rate(MagOutputDataRate::Hz1).bits(), 0x01);
+        assert_eq!(reg.with_output_rate(MagOutputDataRate::Hz1000).bits(), 0x07);
+
+        let reg = reg
+            .with_set_period(SetResetPeriod::Every100)
+            .with_output_rate(MagOutputDataRate::Hz50)
+            | InternalControl2::CMM_EN;
+        assert_eq!(reg.bits(), 0x3C);
+        assert_eq!(reg.set_period(), SetResetPeriod::Every100);
+        assert_eq!(reg.output_rate(), MagOutputDataRate::Hz50);
+    }
+
+    #[test]
+    fn control3_bits() {
+        assert_eq!(InternalControl3::ST_ENP.bits(), 0x02);
+        assert_eq!(InternalControl3::ST_ENM.bits(), 0x04);
+        assert_eq!(InternalControl3::SPI_3W.bits(), 0x40);
+    }
+}
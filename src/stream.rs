@@ -0,0 +1,243 @@
+//! Timestamped continuous mode samples
+//!
+//! [`Samples`] waits on the INT pin for each measurement of an async driver
+//! in continuous mode, instead of polling the status register. A delay
+//! provider bounds each wait, so a stuck INT line ends in
+//! [`Error::Timeout`](crate::Error::Timeout) instead of hanging. Every
+//! [`Sample`] carries a timestamp from a caller-supplied [`Clock`] and the
+//! number of measurements missed since the previous one, found by comparing
+//! the elapsed time with the configured output data rate.
+//!
+//! ```no_run
+//! # async fn run<DI, CommE, P, D>(mut mag: mmc5983_rs::Mmc5983Async<DI, mmc5983_rs::mode::Continuous>, int: P, delay: D)
+//! # where
+//! #     DI: mmc5983_rs::interface::ReadDataAsync<Error = mmc5983_rs::Error<CommE>>
+//! #         + mmc5983_rs::interface::WriteDataAsync<Error = mmc5983_rs::Error<CommE>>,
+//! #     P: embedded_hal_async::digital::Wait,
+//! #     D: embedded_hal_async::delay::DelayNs,
+//! # {
+//! # fn now_us() -> u64 { 0 }
+//! // With embassy: || embassy_time::Instant::now().as_micros()
+//! let mut samples = mag.samples(int, delay, now_us);
+//! loop {
+//!     if let Ok(sample) = samples.next_sample().await {
+//!         if sample.overrun() {
+//!             // Fell behind by sample.missed() measurements
+//!         }
+//!         let (x, y, z) = sample.field().gauss();
+//!     }
+//! }
+//! # }
+//! ```
+
+use embedded_hal_async::{delay::DelayNs, digital::Wait};
+
+use crate::{
+    interface::{ReadDataAsync, WriteDataAsync},
+    mode, Error, MagneticFieldI32, Mmc5983Async, StatusFlags,
+};
+
+/// Monotonic time source used to timestamp samples
+pub trait Clock {
+    /// Current time in microseconds
+    fn now_us(&mut self) -> u64;
+}
+
+impl<F: FnMut() -> u64> Clock for F {
+    fn now_us(&mut self) -> u64 {
+        self()
+    }
+}
+
+/// A timestamped continuous mode measurement
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Sample {
+    field: MagneticFieldI32,
+    timestamp_us: u64,
+    sequence: u32,
+    missed: u32,
+}
+
+impl Sample {
+    /// Measured magnetic field
+    pub const fn field(&self) -> MagneticFieldI32 {
+        self.field
+    }
+
+    /// Time the measurement was read, in microseconds of the [`Clock`]
+    pub const fn timestamp_us(&self) -> u64 {
+        self.timestamp_us
+    }
+
+    /// Index of the measurement since the stream started, counting the
+    /// missed ones but not gaps skipped with [`Samples::resync`]
+    pub const fn sequence(&self) -> u32 {
+        self.sequence
+    }
+
+    /// Number of measurements missed since the previous sample
+    pub const fn missed(&self) -> u32 {
+        self.missed
+    }
+
+    /// Whether measurements were missed since the previous sample
+    pub const fn overrun(&self) -> bool {
+        self.missed > 0
+    }
+}
+
+/// Stream of timestamped samples, see [`Mmc5983Async::samples`]
+#[derive(Debug)]
+pub struct Samples<'a, DI, P, D, C> {
+    mag: &'a mut Mmc5983Async<DI, mode::Continuous>,
+    int: P,
+    delay: D,
+    clock: C,
+    period_us: u64,
+    last: Option<u64>,
+    sequence: Option<u32>,
+}
+
+impl<DI> Mmc5983Async<DI, mode::Continuous> {
+    /// Stream the measurements, waiting on the INT pin `int` and
+    /// timestamping them with `clock`.
+    ///
+    /// The measurement done interrupt must be enabled, which `init` does.
+    /// `delay` bounds each wait like in
+    /// [`magnetic_field_on_interrupt`](Mmc5983Async::magnetic_field_on_interrupt).
+    pub fn samples<P: Wait, D: DelayNs, C: Clock>(
+        &mut self,
+        int: P,
+        delay: D,
+        clock: C,
+    ) -> Samples<'_, DI, P, D, C> {
+        let period_us = self.ctrl_reg2.output_rate().period_us().into();
+        Samples {
+            mag: self,
+            int,
+            delay,
+            clock,
+            period_us,
+            last: None,
+            sequence: None,
+        }
+    }
+}
+
+impl<DI, CommE, P, D, C> Samples<'_, DI, P, D, C>
+where
+    DI: ReadDataAsync<Error = Error<CommE>> + WriteDataAsync<Error = Error<CommE>>,
+    P: Wait,
+    D: DelayNs,
+    C: Clock,
+{
+    /// Wait for the next measurement.
+    ///
+    /// A measurement that completed while the previous one was being
+    /// processed is returned immediately. Measurements overwritten before
+    /// they were read are reported through [`Sample::missed`].
+    pub async fn next_sample(&mut self) -> Result<Sample, Error<CommE>> {
+        if self.sequence.is_none() {
+            // An interrupt still pending from before the stream started
+            // would return an old measurement as the first sample
+            self.mag.clear_interrupts(StatusFlags::MEAS_M_DONE).await?;
+        }
+        let field = self
+            .mag
+            .read_on_interrupt(&mut self.int, &mut self.delay)
+            .await?;
+        let now = self.clock.now_us();
+
+        let missed = match self.last {
+            Some(last) => {
+                let periods = (now.saturating_sub(last) + self.period_us / 2) / self.period_us;
+                u32::try_from(periods.saturating_sub(1)).unwrap_or(u32::MAX)
+            }
+            None => 0,
+        };
+        let sequence = match self.sequence {
+            Some(sequence) => sequence.wrapping_add(missed).wrapping_add(1),
+            None => 0,
+        };
+        self.last = Some(now);
+        self.sequence = Some(sequence);
+
+        Ok(Sample {
+            field,
+            timestamp_us: now,
+            sequence,
+            missed,
+        })
+    }
+
+    /// Forget the previous sample, e.g. after pausing the stream on purpose,
+    /// so that the gap is not reported as an overrun
+    pub fn resync(&mut self) {
+        self.last = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal::digital::InputPin;
+
+    use crate::{
+        mock::{block_on, init_i2c, sim_with_field, MockDelay},
+        MagOutputDataRate,
+    };
+
+    #[test]
+    fn stream_reports_overrun() {
+        let sim = sim_with_field((100, -200, 300));
+        let mag = init_i2c(&sim).into_async();
+        let mut mag = block_on(mag.into_continuous(MagOutputDataRate::Hz100, None)).unwrap();
+
+        let mut times = [1_000, 11_000, 20_500, 52_000, 500_000].into_iter();
+        let mut samples = mag.samples(sim.int_pin(), MockDelay, move || times.next().unwrap());
+        let mut next = || block_on(samples.next_sample()).unwrap();
+
+        let first = next();
+        assert_eq!(first.field().counts(), (100, -200, 300));
+        assert_eq!((first.timestamp_us(), first.sequence()), (1_000, 0));
+        assert!(!first.overrun());
+        assert_eq!((next().sequence(), next().sequence()), (1, 2));
+        let late = next();
+        assert_eq!((late.missed(), late.sequence()), (2, 5));
+        assert!(late.overrun());
+
+        samples.resync();
+        let resynced = block_on(samples.next_sample()).unwrap();
+        assert_eq!((resynced.missed(), resynced.sequence()), (0, 6));
+    }
+
+    #[test]
+    fn stream_skips_interrupt_pending_before_start() {
+        let sim = sim_with_field((1, 2, 3));
+        sim.set_latency(10);
+        let mag = init_i2c(&sim).into_async();
+        let mut mag = block_on(mag.into_continuous(MagOutputDataRate::Hz100, None)).unwrap();
+        let mut int = sim.int_pin();
+        while !int.is_high().unwrap() {}
+        sim.set_field((4, 5, 6));
+
+        let mut samples = mag.samples(sim.int_pin(), MockDelay, || 0);
+        let first = block_on(samples.next_sample()).unwrap();
+        assert_eq!(first.field().counts(), (4, 5, 6));
+    }
+
+    #[test]
+    fn measurement_completing_during_read_is_kept() {
+        let sim = sim_with_field((1, 2, 3));
+        sim.set_latency(2);
+        let mag = init_i2c(&sim).into_async();
+        let mut mag = block_on(mag.into_continuous(MagOutputDataRate::Hz100, None)).unwrap();
+
+        let mut samples = mag.samples(sim.int_pin(), MockDelay, || 0);
+        block_on(samples.next_sample()).unwrap();
+        // The next measurement completed while the output registers were
+        // read, after its interrupt had been cleared
+        assert!(sim.interrupt());
+    }
+}
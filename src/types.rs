@@ -1,9 +1,12 @@
 use bitflags::bitflags;
+use embedded_hal::digital::ErrorKind as PinErrorKind;
 
+use crate::config::ConfigError;
 use crate::register_address::{ProductId1, RegRead};
 
 /// All possible errors in this crate
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error<CommE> {
     /// I²C / SPI communication error
     Comm(CommE),
@@ -11,6 +14,13 @@ pub enum Error<CommE> {
     InvalidInputData,
     /// Invalid input data provided
     InvalidId(ProductId),
+    /// The device did not report completion within the poll budget, or the
+    /// INT pin was not asserted in time
+    Timeout,
+    /// Error reading the interrupt pin
+    Pin(PinErrorKind),
+    /// Invalid device configuration
+    Config(ConfigError),
 }
 
 impl<CommE> From<CommE> for Error<CommE> {
@@ -31,6 +41,8 @@ pub mod mode {
 
 /// A ProductId - used to identify the device.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct ProductId {
     raw: u8,
 }
@@ -51,12 +63,94 @@ impl ProductId {
     }
 }
 
-/// A magnetic field measurement.
+/// Magnetic field output resolution
 #[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Resolution {
+    /// 16-bit output read from Xout0..Zout1, 4096 counts per Gauss
+    Bits16,
+    /// 18-bit output including XYZout2, 16384 counts per Gauss
+    #[default]
+    Bits18,
+}
+
+impl Resolution {
+    /// Output code of a zero field
+    #[inline]
+    pub const fn null_field(&self) -> u32 {
+        MagneticField::NULL_FIELD >> self.dropped_bits()
+    }
+
+    /// Counts per Gauss
+    #[inline]
+    pub const fn sensitivity(&self) -> f32 {
+        match self {
+            Resolution::Bits16 => 4096.0,
+            Resolution::Bits18 => 16384.0,
+        }
+    }
+
+    /// Number of output registers to read, starting at Xout0
+    #[inline]
+    pub(crate) const fn output_len(&self) -> usize {
+        match self {
+            Resolution::Bits16 => 6,
+            Resolution::Bits18 => 7,
+        }
+    }
+
+    /// Number of low bits missing compared to the 18-bit output
+    #[inline]
+    const fn dropped_bits(&self) -> u32 {
+        match self {
+            Resolution::Bits16 => 2,
+            Resolution::Bits18 => 0,
+        }
+    }
+}
+
+/// Magnetic field channels enabled for measurement
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Channels {
+    /// X, Y and Z are measured
+    #[default]
+    Xyz,
+    /// Only X is measured, Y and Z are inhibited
+    X,
+    /// Only Y and Z are measured, X is inhibited
+    Yz,
+}
+
+impl Channels {
+    /// Whether the X channel is measured
+    #[inline]
+    pub const fn x(&self) -> bool {
+        !matches!(self, Channels::Yz)
+    }
+
+    /// Whether the Y and Z channels are measured
+    #[inline]
+    pub const fn yz(&self) -> bool {
+        !matches!(self, Channels::X)
+    }
+}
+
+/// A raw magnetic field measurement, as read from the output registers.
+///
+/// The sensor reports unsigned counts where [`Resolution::null_field`]
+/// corresponds to a zero field. Inhibited channels read as the null field.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct MagneticField {
     pub(crate) x: u32,
     pub(crate) y: u32,
     pub(crate) z: u32,
+    pub(crate) resolution: Resolution,
+    pub(crate) channels: Channels,
 }
 
 impl RegRead<(u32, u32, u32)> for MagneticField {
@@ -67,13 +161,31 @@ impl RegRead<(u32, u32, u32)> for MagneticField {
 
     #[inline(always)]
     fn from_data((x, y, z): (u32, u32, u32)) -> Self::Output {
-        Self { x, y, z }
+        Self {
+            x,
+            y,
+            z,
+            resolution: Resolution::Bits18,
+            channels: Channels::Xyz,
+        }
     }
 }
 
 impl MagneticField {
-    const SENSITIVITY: f32 = 16384.0; // counts per Gauss in 18-bit mode
-    const COUNTS_TO_GAUSS: f32 = 1.0 / Self::SENSITIVITY;
+    /// Output code of a zero field in 18-bit mode
+    pub const NULL_FIELD: u32 = 131072;
+
+    /// Resolution the measurement was taken with
+    #[inline]
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    /// Channels that were measured
+    #[inline]
+    pub fn channels(&self) -> Channels {
+        self.channels
+    }
 
     /// Raw magnetic field in X-direction
     #[inline]
@@ -96,19 +208,181 @@ impl MagneticField {
     /// Magnetic field in X-direction in Gauss with proper scaling
     #[inline]
     pub fn x_gauss(&self) -> f32 {
-        self.x_raw() as i32 as f32 * Self::COUNTS_TO_GAUSS
+        self.centred().x_gauss()
     }
 
     /// Magnetic field in Y-direction in Gauss with proper scaling
     #[inline]
     pub fn y_gauss(&self) -> f32 {
-        self.y_raw() as i32 as f32 * Self::COUNTS_TO_GAUSS
+        self.centred().y_gauss()
     }
 
     /// Magnetic field in Z-direction in Gauss with proper scaling
     #[inline]
     pub fn z_gauss(&self) -> f32 {
-        self.z_raw() as i32 as f32 * Self::COUNTS_TO_GAUSS
+        self.centred().z_gauss()
+    }
+
+    /// Magnetic field in X-, Y- and Z-directions in Gauss.
+    #[inline]
+    pub fn gauss(&self) -> (f32, f32, f32) {
+        (self.x_gauss(), self.y_gauss(), self.z_gauss())
+    }
+
+    /// Replace stale register contents of inhibited channels by the null field
+    #[inline]
+    pub(crate) const fn with_inhibited_nulled(mut self) -> Self {
+        let null = self.resolution.null_field();
+        if !self.channels.x() {
+            self.x = null;
+        }
+        if !self.channels.yz() {
+            self.y = null;
+            self.z = null;
+        }
+        self
+    }
+
+    /// Raw counts converted to signed values relative to the null point
+    #[inline]
+    const fn signed_counts(&self) -> (i32, i32, i32) {
+        let null = self.resolution.null_field() as i32;
+        (
+            self.x as i32 - null,
+            self.y as i32 - null,
+            self.z as i32 - null,
+        )
+    }
+
+    /// Signed magnetic field, centred on the null point.
+    #[inline]
+    pub fn centred(&self) -> MagneticFieldI32 {
+        MagneticFieldI32::from_raw(*self, Offset::default())
+    }
+}
+
+/// A signed magnetic field measurement, centred on zero field.
+///
+/// The raw register counts the measurement was derived from are kept
+/// alongside and can be retrieved with [`MagneticFieldI32::raw`].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MagneticFieldI32 {
+    pub(crate) x: i32,
+    pub(crate) y: i32,
+    pub(crate) z: i32,
+    pub(crate) raw: MagneticField,
+}
+
+impl From<MagneticField> for MagneticFieldI32 {
+    #[inline]
+    fn from(raw: MagneticField) -> Self {
+        raw.centred()
+    }
+}
+
+impl MagneticFieldI32 {
+    const GAUSS_TO_MICROTESLA: f32 = 100.0;
+    const GAUSS_TO_NANOTESLA: f32 = 100_000.0;
+
+    /// Build a signed measurement from raw counts, removing the null point
+    /// and the bridge `offset`.
+    #[inline]
+    pub(crate) fn from_raw(raw: MagneticField, offset: Offset) -> Self {
+        let (x, y, z) = raw.signed_counts();
+        let shift = raw.resolution.dropped_bits();
+        let (cx, cyz) = (raw.channels.x(), raw.channels.yz());
+        Self {
+            x: if cx { x - (offset.x >> shift) } else { 0 },
+            y: if cyz { y - (offset.y >> shift) } else { 0 },
+            z: if cyz { z - (offset.z >> shift) } else { 0 },
+            raw,
+        }
+    }
+
+    /// Build a signed measurement from raw counts taken after a RESET
+    /// operation, which inverts the sign of the field but not of the offset.
+    #[inline]
+    pub(crate) fn from_reset(raw: MagneticField, offset: Offset) -> Self {
+        let field = Self::from_raw(raw, offset);
+        Self {
+            x: -field.x,
+            y: -field.y,
+            z: -field.z,
+            raw,
+        }
+    }
+
+    /// Raw register counts this measurement was derived from
+    #[inline]
+    pub fn raw(&self) -> MagneticField {
+        self.raw
+    }
+
+    /// Resolution the measurement was taken with
+    #[inline]
+    pub fn resolution(&self) -> Resolution {
+        self.raw.resolution
+    }
+
+    /// Channels that were measured
+    #[inline]
+    pub fn channels(&self) -> Channels {
+        self.raw.channels
+    }
+
+    /// Signed magnetic field in X-direction, in counts
+    #[inline]
+    pub fn x(&self) -> i32 {
+        self.x
+    }
+
+    /// Signed magnetic field in Y-direction, in counts
+    #[inline]
+    pub fn y(&self) -> i32 {
+        self.y
+    }
+
+    /// Signed magnetic field in Z-direction, in counts
+    #[inline]
+    pub fn z(&self) -> i32 {
+        self.z
+    }
+
+    /// Signed magnetic field in X-, Y- and Z-directions, in counts
+    #[inline]
+    pub fn counts(&self) -> (i32, i32, i32) {
+        (self.x, self.y, self.z)
+    }
+
+    /// Signed magnetic field in counts, `None` for inhibited channels
+    #[inline]
+    pub fn checked_counts(&self) -> (Option<i32>, Option<i32>, Option<i32>) {
+        let channels = self.channels();
+        (
+            channels.x().then_some(self.x),
+            channels.yz().then_some(self.y),
+            channels.yz().then_some(self.z),
+        )
+    }
+
+    /// Magnetic field in X-direction in Gauss
+    #[inline]
+    pub fn x_gauss(&self) -> f32 {
+        self.x as f32 / self.resolution().sensitivity()
+    }
+
+    /// Magnetic field in Y-direction in Gauss
+    #[inline]
+    pub fn y_gauss(&self) -> f32 {
+        self.y as f32 / self.resolution().sensitivity()
+    }
+
+    /// Magnetic field in Z-direction in Gauss
+    #[inline]
+    pub fn z_gauss(&self) -> f32 {
+        self.z as f32 / self.resolution().sensitivity()
     }
 
     /// Magnetic field in X-, Y- and Z-directions in Gauss.
@@ -116,10 +390,250 @@ impl MagneticField {
     pub fn gauss(&self) -> (f32, f32, f32) {
         (self.x_gauss(), self.y_gauss(), self.z_gauss())
     }
+
+    /// Magnetic field in Gauss, `None` for inhibited channels
+    #[inline]
+    pub fn checked_gauss(&self) -> (Option<f32>, Option<f32>, Option<f32>) {
+        let channels = self.channels();
+        let (x, y, z) = self.gauss();
+        (
+            channels.x().then_some(x),
+            channels.yz().then_some(y),
+            channels.yz().then_some(z),
+        )
+    }
+
+    /// Magnetic field in X-, Y- and Z-directions in microtesla.
+    #[inline]
+    pub fn microtesla(&self) -> (f32, f32, f32) {
+        let (x, y, z) = self.gauss();
+        (
+            x * Self::GAUSS_TO_MICROTESLA,
+            y * Self::GAUSS_TO_MICROTESLA,
+            z * Self::GAUSS_TO_MICROTESLA,
+        )
+    }
+
+    /// Magnetic field in X-, Y- and Z-directions in nanotesla.
+    #[inline]
+    pub fn nanotesla(&self) -> (f32, f32, f32) {
+        let (x, y, z) = self.gauss();
+        (
+            x * Self::GAUSS_TO_NANOTESLA,
+            y * Self::GAUSS_TO_NANOTESLA,
+            z * Self::GAUSS_TO_NANOTESLA,
+        )
+    }
+}
+
+/// Bridge offset found by the SET/RESET procedure.
+///
+/// Stored as signed 18-bit counts relative to the null point, so an ideal
+/// sensor has a zero offset. It is scaled down when applied to 16-bit
+/// measurements.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Offset {
+    pub(crate) x: i32,
+    pub(crate) y: i32,
+    pub(crate) z: i32,
+}
+
+impl Offset {
+    /// Compute the offset from a measurement taken after a SET operation and
+    /// one taken after a RESET operation.
+    ///
+    /// The SET measurement reads `H + offset` and the RESET one `-H + offset`,
+    /// so their mean is the offset.
+    pub fn from_set_reset(set: MagneticField, reset: MagneticField) -> Self {
+        let (sx, sy, sz) = set.signed_counts();
+        let (rx, ry, rz) = reset.signed_counts();
+        let (ss, rs) = (
+            set.resolution.dropped_bits(),
+            reset.resolution.dropped_bits(),
+        );
+        Self {
+            x: ((sx << ss) + (rx << rs)) / 2,
+            y: ((sy << ss) + (ry << rs)) / 2,
+            z: ((sz << ss) + (rz << rs)) / 2,
+        }
+    }
+
+    /// Offset in X-direction, in 18-bit counts
+    #[inline]
+    pub fn x(&self) -> i32 {
+        self.x
+    }
+
+    /// Offset in Y-direction, in 18-bit counts
+    #[inline]
+    pub fn y(&self) -> i32 {
+        self.y
+    }
+
+    /// Offset in Z-direction, in 18-bit counts
+    #[inline]
+    pub fn z(&self) -> i32 {
+        self.z
+    }
+
+    /// Offset in X-, Y- and Z-directions in Gauss.
+    #[inline]
+    pub fn gauss(&self) -> (f32, f32, f32) {
+        let sensitivity = Resolution::Bits18.sensitivity();
+        (
+            self.x as f32 / sensitivity,
+            self.y as f32 / sensitivity,
+            self.z as f32 / sensitivity,
+        )
+    }
+}
+
+/// Register contents read back from the device
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RegisterDump {
+    pub(crate) output: [u8; 7],
+    pub(crate) temperature: u8,
+    pub(crate) status: u8,
+    pub(crate) product_id: u8,
+}
+
+impl RegisterDump {
+    /// Xout0 to XYZout2 (`0x00`-`0x06`)
+    #[inline]
+    pub fn output(&self) -> [u8; 7] {
+        self.output
+    }
+
+    /// TOUT (`0x07`)
+    #[inline]
+    pub fn temperature(&self) -> u8 {
+        self.temperature
+    }
+
+    /// Status (`0x08`)
+    #[inline]
+    pub fn status(&self) -> u8 {
+        self.status
+    }
+
+    /// Product ID 1 (`0x2F`)
+    #[inline]
+    pub fn product_id(&self) -> u8 {
+        self.product_id
+    }
+}
+
+/// State of the one-shot SET/RESET offset tracking
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct OffsetTracking {
+    /// A RESET measurement is taken every `period` samples
+    pub(crate) period: u32,
+    /// Samples since the last RESET measurement
+    pub(crate) count: u32,
+    /// Latest measurement taken after a SET operation
+    pub(crate) last_set: Option<MagneticField>,
+}
+
+impl OffsetTracking {
+    pub(crate) const fn new() -> Self {
+        Self {
+            period: 2,
+            count: 0,
+            last_set: None,
+        }
+    }
+}
+
+/// Accepted range of the self-test field delta on each axis, in Gauss
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SelfTestLimits {
+    min: f32,
+    max: f32,
+}
+
+impl SelfTestLimits {
+    /// Accept axes whose delta magnitude lies within `min..=max` Gauss
+    pub const fn new(min: f32, max: f32) -> Self {
+        Self { min, max }
+    }
+
+    /// Smallest accepted delta in Gauss
+    #[inline]
+    pub fn min(&self) -> f32 {
+        self.min
+    }
+
+    /// Largest accepted delta in Gauss
+    #[inline]
+    pub fn max(&self) -> f32 {
+        self.max
+    }
+
+    /// Whether a delta lies within the limits
+    #[inline]
+    pub fn contains(&self, delta: f32) -> bool {
+        (self.min..=self.max).contains(&delta.abs())
+    }
+}
+
+/// Result of the built-in self-test
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SelfTestReport {
+    pub(crate) delta: (f32, f32, f32),
+    pub(crate) channels: Channels,
+    pub(crate) limits: SelfTestLimits,
+}
+
+impl SelfTestReport {
+    /// Field produced by the self-test current in X-, Y- and Z-directions in
+    /// Gauss.
+    ///
+    /// This is half the difference between the readings taken with the
+    /// positive and the negative self-test current, so the ambient field
+    /// cancels out.
+    #[inline]
+    pub fn delta_gauss(&self) -> (f32, f32, f32) {
+        self.delta
+    }
+
+    /// Limits the deltas were checked against
+    #[inline]
+    pub fn limits(&self) -> SelfTestLimits {
+        self.limits
+    }
+
+    /// Whether the X-, Y- and Z-axis deltas are within the limits.
+    ///
+    /// Inhibited channels always pass.
+    pub fn axes_passed(&self) -> (bool, bool, bool) {
+        let (x, y, z) = self.delta;
+        let (cx, cyz) = (self.channels.x(), self.channels.yz());
+        (
+            !cx || self.limits.contains(x),
+            !cyz || self.limits.contains(y),
+            !cyz || self.limits.contains(z),
+        )
+    }
+
+    /// Whether every measured axis passed
+    pub fn passed(&self) -> bool {
+        let (x, y, z) = self.axes_passed();
+        x && y && z
+    }
 }
 
 /// Magnetometer output data rate/bandwidth
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum BandwidthMode {
     /// BW = 00, 100Hz bandwidth
     Hz100,
@@ -131,8 +645,41 @@ pub enum BandwidthMode {
     Hz800,
 }
 
+impl BandwidthMode {
+    /// Lowest-noise bandwidth supporting the continuous mode output data
+    /// `rate`.
+    ///
+    /// The measurement time must fit within one output period: 8ms at 100Hz,
+    /// 4ms at 200Hz, 2ms at 400Hz and 0.5ms at 800Hz bandwidth. Lower
+    /// bandwidths average longer and are less noisy.
+    pub const fn for_output_rate(rate: MagOutputDataRate) -> Self {
+        match rate {
+            MagOutputDataRate::Hz200 => BandwidthMode::Hz200,
+            MagOutputDataRate::Hz1000 => BandwidthMode::Hz800,
+            _ => BandwidthMode::Hz100,
+        }
+    }
+
+    /// Whether continuous mode can run at `rate` with this bandwidth
+    pub const fn supports(&self, rate: MagOutputDataRate) -> bool {
+        *self as u8 >= Self::for_output_rate(rate) as u8
+    }
+
+    /// Duration of one measurement in microseconds
+    pub const fn measurement_time_us(&self) -> u32 {
+        match self {
+            BandwidthMode::Hz100 => 8_000,
+            BandwidthMode::Hz200 => 4_000,
+            BandwidthMode::Hz400 => 2_000,
+            BandwidthMode::Hz800 => 500,
+        }
+    }
+}
+
 /// Magnetometer operating mode
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum MagMode {
     /// Continuous measurement mode
     Continuous {
@@ -140,13 +687,20 @@ pub enum MagMode {
         frequency: MagOutputDataRate,
         /// Set/Reset period
         set_period: Option<SetResetPeriod>,
+        /// Automatic SET/RESET on each measurement (`AUTO_SR`)
+        auto_sr: bool,
     },
     /// Single measurement mode
-    OneShot,
+    OneShot {
+        /// Automatic SET/RESET on each measurement (`AUTO_SR`)
+        auto_sr: bool,
+    },
 }
 
 /// Magnetometer output data rate for continuous mode
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum MagOutputDataRate {
     /// 1 Hz
     Hz1,
@@ -158,9 +712,9 @@ pub enum MagOutputDataRate {
     Hz50,
     /// 100 Hz
     Hz100,
-    /// 200 Hz (when BW=01)
+    /// 200 Hz, requires BW=01 or higher
     Hz200,
-    /// 1000 Hz (when BW=11)
+    /// 1000 Hz, requires BW=11
     Hz1000,
 }
 
@@ -177,10 +731,25 @@ impl MagOutputDataRate {
             MagOutputDataRate::Hz1000 => 0b111,
         }
     }
+
+    /// Time between two measurements in microseconds
+    pub const fn period_us(&self) -> u32 {
+        match self {
+            MagOutputDataRate::Hz1 => 1_000_000,
+            MagOutputDataRate::Hz10 => 100_000,
+            MagOutputDataRate::Hz20 => 50_000,
+            MagOutputDataRate::Hz50 => 20_000,
+            MagOutputDataRate::Hz100 => 10_000,
+            MagOutputDataRate::Hz200 => 5_000,
+            MagOutputDataRate::Hz1000 => 1_000,
+        }
+    }
 }
 
 /// Period for automatic SET operations
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum SetResetPeriod {
     /// Every measurement
     Every1 = 0,
@@ -201,28 +770,148 @@ pub enum SetResetPeriod {
 }
 
 bitflags! {
+    /// Status register bits
+    ///
+    /// Reading the register clears nothing. The done bits are cleared when
+    /// the next measurement of the same kind is triggered, and writing 1 to
+    /// them de-asserts the INT pin. Bits not listed here are reserved.
     #[derive(Debug, Default, Copy, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct StatusFlags: u8 {
-        /// Measurement done
+        /// Magnetic field measurement done, write 1 to clear its interrupt
         const MEAS_M_DONE = 0b00000001;
-        /// Temperature measurement done
+        /// Temperature measurement done, write 1 to clear its interrupt
         const MEAS_T_DONE = 0b00000010;
-        /// OTP read done
+        /// OTP memory read done, set until power down
         const OTP_READ_DONE = 0b00010000;
     }
 }
 
+impl StatusFlags {
+    /// Bits whose interrupt is cleared by writing 1 to them
+    pub const INTERRUPTS: Self = Self::MEAS_M_DONE.union(Self::MEAS_T_DONE);
+}
+
+/// An event reported by the status register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum StatusEvent {
+    /// A magnetic field measurement completed
+    MeasurementDone,
+    /// A temperature measurement completed
+    TemperatureDone,
+    /// The OTP memory was read, after power up or an `OTP_READ` command
+    OtpReadDone,
+}
+
+impl StatusEvent {
+    /// All events, in status register bit order
+    pub const ALL: [Self; 3] = [
+        Self::MeasurementDone,
+        Self::TemperatureDone,
+        Self::OtpReadDone,
+    ];
+
+    /// Status register bit of the event
+    pub const fn flag(&self) -> StatusFlags {
+        match self {
+            Self::MeasurementDone => StatusFlags::MEAS_M_DONE,
+            Self::TemperatureDone => StatusFlags::MEAS_T_DONE,
+            Self::OtpReadDone => StatusFlags::OTP_READ_DONE,
+        }
+    }
+
+    /// How the event is cleared
+    pub const fn clear_mode(&self) -> ClearMode {
+        match self {
+            Self::MeasurementDone | Self::TemperatureDone => ClearMode::WriteOne,
+            Self::OtpReadDone => ClearMode::Sticky,
+        }
+    }
+}
+
+/// How a status bit is cleared
+///
+/// No status bit is cleared by reading the register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ClearMode {
+    /// The interrupt is held until 1 is written to the bit, the bit itself
+    /// is cleared when the next measurement is triggered
+    WriteOne,
+    /// The bit stays set until power down and never raises an interrupt
+    Sticky,
+}
+
 /// Device status
+///
+/// A snapshot of the status register. Reading it has no side effects, use
+/// `clear_interrupts` or `acknowledge` on the driver to clear the interrupts.
 #[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Status {
     flags: StatusFlags,
 }
 
+#[cfg(feature = "defmt")]
+impl defmt::Format for Status {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "Status {{ meas_done: {}, temp_done: {}, otp_read_done: {}, reserved: {=u8:#04x} }}",
+            self.meas_done(),
+            self.temp_done(),
+            self.otp_read_done(),
+            self.reserved()
+        )
+    }
+}
+
 impl Status {
     pub(crate) const fn new(flags: StatusFlags) -> Self {
         Self { flags }
     }
 
+    /// Status register bits, including reserved ones
+    #[inline]
+    pub const fn flags(&self) -> StatusFlags {
+        self.flags
+    }
+
+    /// Raw status register value
+    #[inline]
+    pub const fn raw(&self) -> u8 {
+        self.flags.bits()
+    }
+
+    /// Reserved bits that were set, normally 0
+    #[inline]
+    pub const fn reserved(&self) -> u8 {
+        self.flags.bits() & !StatusFlags::all().bits()
+    }
+
+    /// Interrupts pending until cleared by writing 1
+    #[inline]
+    pub const fn interrupts(&self) -> StatusFlags {
+        self.flags.intersection(StatusFlags::INTERRUPTS)
+    }
+
+    /// Check if `event` is reported
+    #[inline]
+    pub const fn contains(&self, event: StatusEvent) -> bool {
+        self.flags.contains(event.flag())
+    }
+
+    /// Reported events, in status register bit order
+    pub fn events(&self) -> impl Iterator<Item = StatusEvent> {
+        let status = *self;
+        StatusEvent::ALL
+            .into_iter()
+            .filter(move |event| status.contains(*event))
+    }
+
     /// Check if magnetic measurement is complete
     #[inline]
     pub const fn meas_done(&self) -> bool {
@@ -244,6 +933,8 @@ impl Status {
 
 /// A temperature measurement.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Temperature {
     pub(crate) raw: u8,
 }
@@ -275,3 +966,160 @@ impl Temperature {
         -75.0 + (self.raw as f32) * 0.8
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NULL: u32 = MagneticField::NULL_FIELD;
+
+    fn raw(x: u32, y: u32, z: u32) -> MagneticField {
+        MagneticField {
+            x,
+            y,
+            z,
+            resolution: Resolution::Bits18,
+            channels: Channels::Xyz,
+        }
+    }
+
+    fn raw16(x: u32, y: u32, z: u32) -> MagneticField {
+        MagneticField {
+            x,
+            y,
+            z,
+            resolution: Resolution::Bits16,
+            channels: Channels::Xyz,
+        }
+    }
+
+    #[test]
+    fn bandwidth_for_output_rate() {
+        use MagOutputDataRate::*;
+        assert_eq!(BandwidthMode::for_output_rate(Hz100), BandwidthMode::Hz100);
+        assert_eq!(BandwidthMode::for_output_rate(Hz200), BandwidthMode::Hz200);
+        assert_eq!(BandwidthMode::for_output_rate(Hz1000), BandwidthMode::Hz800);
+        assert!(BandwidthMode::Hz400.supports(Hz200));
+        assert!(!BandwidthMode::Hz400.supports(Hz1000));
+        assert!(BandwidthMode::Hz100.supports(Hz1));
+    }
+
+    #[test]
+    fn self_test_report() {
+        let limits = SelfTestLimits::new(0.5, 2.0);
+        let mut report = SelfTestReport {
+            delta: (1.0, -1.5, 0.1),
+            channels: Channels::Xyz,
+            limits,
+        };
+        assert_eq!(report.axes_passed(), (true, true, false));
+        assert!(!report.passed());
+
+        report.channels = Channels::X;
+        assert!(report.passed());
+    }
+
+    #[test]
+    fn null_point_is_zero_field() {
+        let field = raw(NULL, NULL, NULL).centred();
+        assert_eq!(field.counts(), (0, 0, 0));
+        assert_eq!(field.gauss(), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn centred_field_is_signed() {
+        let field = raw(NULL + 16384, NULL - 16384, 0).centred();
+        assert_eq!(field.counts(), (16384, -16384, -(NULL as i32)));
+        assert_eq!(field.gauss(), (1.0, -1.0, -8.0));
+        assert_eq!(field.microtesla(), (100.0, -100.0, -800.0));
+        assert_eq!(field.raw(), raw(NULL + 16384, NULL - 16384, 0));
+    }
+
+    #[test]
+    fn sixteen_bit_scaling() {
+        let null = Resolution::Bits16.null_field();
+        assert_eq!(null, 32768);
+        let field = raw16(null + 4096, null - 2048, null).centred();
+        assert_eq!(field.counts(), (4096, -2048, 0));
+        assert_eq!(field.gauss(), (1.0, -0.5, 0.0));
+    }
+
+    #[test]
+    fn offset_is_scaled_to_resolution() {
+        let set = raw16(32768 + 100, 32768, 32768);
+        let reset = raw16(32768 - 60, 32768, 32768);
+        let offset = Offset::from_set_reset(set, reset);
+        assert_eq!(offset.x(), 80);
+
+        let field = MagneticFieldI32::from_raw(raw16(32768 + 100, 32768, 32768), offset);
+        assert_eq!(field.counts(), (80, 0, 0));
+        let field = MagneticFieldI32::from_raw(raw(NULL + 400, NULL, NULL), offset);
+        assert_eq!(field.counts(), (320, 0, 0));
+    }
+
+    #[test]
+    fn offset_from_set_reset_above_null() {
+        // H = +1000 counts, offset = +200 counts
+        let set = raw(NULL + 1200, NULL + 1200, NULL + 1200);
+        let reset = raw(NULL - 800, NULL - 800, NULL - 800);
+        let offset = Offset::from_set_reset(set, reset);
+        assert_eq!((offset.x(), offset.y(), offset.z()), (200, 200, 200));
+    }
+
+    #[test]
+    fn offset_from_set_reset_below_null() {
+        // H = +300 counts, offset = -500 counts
+        let set = raw(NULL - 200, NULL - 200, NULL - 200);
+        let reset = raw(NULL - 800, NULL - 800, NULL - 800);
+        let offset = Offset::from_set_reset(set, reset);
+        assert_eq!((offset.x(), offset.y(), offset.z()), (-500, -500, -500));
+    }
+
+    #[test]
+    fn offset_at_range_limits() {
+        let set = raw(0, (1 << 18) - 1, NULL);
+        let reset = raw(0, (1 << 18) - 1, NULL);
+        let offset = Offset::from_set_reset(set, reset);
+        assert_eq!(
+            (offset.x(), offset.y(), offset.z()),
+            (-(NULL as i32), NULL as i32 - 1, 0)
+        );
+    }
+
+    #[test]
+    fn status_register_bits() {
+        let status = Status::new(StatusFlags::from_bits_retain(0b1001_0001));
+        assert!(status.contains(StatusEvent::MeasurementDone));
+        assert!(!status.contains(StatusEvent::TemperatureDone));
+        assert_eq!(status.raw(), 0b1001_0001);
+        assert_eq!(status.reserved(), 0b1000_0000);
+        assert_eq!(status.interrupts(), StatusFlags::MEAS_M_DONE);
+        let mut events = status.events();
+        assert_eq!(events.next(), Some(StatusEvent::MeasurementDone));
+        assert_eq!(events.next(), Some(StatusEvent::OtpReadDone));
+        assert_eq!(events.next(), None);
+        assert_eq!(StatusEvent::OtpReadDone.clear_mode(), ClearMode::Sticky);
+    }
+
+    #[test]
+    fn calibrated_field_below_offset_is_negative() {
+        let offset = Offset {
+            x: 200,
+            y: -500,
+            z: 0,
+        };
+        let field = MagneticFieldI32::from_raw(raw(NULL + 100, NULL - 600, NULL - 1), offset);
+        assert_eq!(field.counts(), (-100, -100, -1));
+    }
+
+    #[test]
+    fn calibrated_field_above_offset_is_positive() {
+        let offset = Offset {
+            x: -200,
+            y: 500,
+            z: 0,
+        };
+        let field = MagneticFieldI32::from_raw(raw(NULL - 100, NULL + 600, NULL + 1), offset);
+        assert_eq!(field.counts(), (100, 100, 1));
+    }
+}